@@ -1,12 +1,52 @@
-use std::{ptr::null_mut, collections::VecDeque};
+use std::{ptr::{self, null_mut, NonNull}, collections::VecDeque, cell::Cell, iter::Rev};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+mod cursor;
+mod flatten;
+mod handle;
+mod index;
+mod iter_mut;
+mod layer_iter;
+mod nest;
+mod owned;
+mod path;
+mod precedence;
+mod relocate;
+mod remove;
+mod rewrite;
+mod slice;
+mod splice;
+mod traits;
+mod traverse;
+mod walk;
+mod wrap;
+
+pub use cursor::{Cursor, CursorMut};
+pub use flatten::FlatIter;
+pub use handle::{CheckedHandle, HandleError};
+pub use iter_mut::{BFSItemsMut, BFSNodesMut, DFSItemsMut, DFSNodesMut};
+pub use layer_iter::{
+    BushLayerItemIterMut, BushLayerIter, BushLayerIterMut, BushNodeIterLeft, BushNodeIterRight,
+    BushNodeItemIterLeft, BushNodeItemIterLeftMut, BushNodeItemIterRight, BushNodeItemIterRightMut
+};
+pub use nest::NestError;
+pub use owned::{DrainFilter, IntoBFS, IntoDFS, IntoNodes};
+pub use path::BushPath;
+pub use precedence::{Associativity, Fixity, PrecedenceError};
+pub use relocate::MoveError;
+pub use remove::EmptyChildren;
+pub use rewrite::{RewriteError, RewriteOptions};
+pub use slice::BushSlice;
+pub use traits::IntoIter;
+pub use traverse::{Annotation, AnnotatedBFSIter, AnnotatedDFSIter, AnnotatedLeafIter, AnnotatedPostOrderIter, IterNodes, Levels};
+pub use walk::WalkControl;
 
 
 pub type IterItems<T, It> = std::iter::Map<It, fn(&BushNode<T>) -> &T>;
-pub type IterItemsMut<T, It> = std::iter::Map<It, fn(&mut BushNode<T>) -> &mut T>;
-
-pub struct BushSlice<T>(pub Box<BushNode<T>>, pub Box<BushNode<T>>);
-
 
+/// An unchecked reference to a node of a bush.
+/// The node can only be accessed through the bush that owns it, see `Bush::node` and `Bush::node_mut`.
+/// Use a `CheckedHandle` when the node may have been removed or may belong to another bush.
 pub struct NodeHandle<T> (*const BushNode<T>);
 
 impl<T> NodeHandle<T> {
@@ -16,388 +56,747 @@ impl<T> NodeHandle<T> {
         self.0
     }
 
-
-    #[inline(always)]
-    pub const fn as_ref(&self) -> &BushNode<T> {
-        unsafe { &*self.0 }
-    }
+}
 
 
-    #[inline(always)]
-    pub const fn clone(&self) -> NodeHandle<T> {
-        NodeHandle(self.0)
+impl<T> Clone for NodeHandle<T> {
+    fn clone(&self) -> Self {
+        *self
     }
+}
 
 
-    #[inline(always)]
-    pub fn as_mut(self) -> &'static mut BushNode<T> {
-        unsafe { &mut *(self.0 as *mut BushNode<T>) }
-    }
-
-}
+impl<T> Copy for NodeHandle<T> {}
 
 
 impl<T> PartialEq for NodeHandle<T> {
     fn eq(&self, other: &Self) -> bool {
-        self.0 == other.0
+        ptr::addr_eq(self.0, other.0)
     }
 }
 
 
-pub struct BushNodeItemIterRight<'a, T> {
+impl<T> Eq for NodeHandle<T> {}
 
-    node: Option<&'a BushNode<T>>
 
-}
+/// Bookkeeping shared by the nodes of a layer.
+/// It lives on the heap so that nodes can link back to it even after the owning bush is moved.
+struct Layer<T> {
 
+    first: *mut BushNode<T>,
+    last: *mut BushNode<T>,
 
-pub struct BushNodeItemIterRightMut<'a, T> {
+    /// The node whose children this layer is, null for a bush's root layer and for the children of a detached node
+    parent: *mut BushNode<T>,
 
-    node: Option<&'a mut BushNode<T>>
+    /// Number of nodes in the layer
+    len: usize,
+    /// Cached number of nodes in the layer and in every layer below it, only meaningful if `total_valid`.
+    /// When a layer is invalidated, so are all the layers above it.
+    total: Cell<usize>,
+    total_valid: Cell<bool>,
 
-}
+    /// Unique identifier of the layer, used to recognize checked handles of other bushes
+    id: u64,
+    /// Incremented whenever nodes are removed from this layer or from any layer below it
+    generation: u64,
 
+    /// The layer this one was merged into, null while the layer is live.
+    /// A whole layer is moved by retiring it instead of updating each of its nodes, which keep pointing to it.
+    forward: Cell<*mut Layer<T>>,
+    /// The retired layers forwarding to this one, kept alive as long as nodes may point to them
+    retired: Vec<NonNull<Layer<T>>>,
 
-pub struct BushNodeItemIterLeft<'a, T> {
+}
 
-    node: Option<&'a BushNode<T>>
 
-}
+/// Source of unique layer identifiers
+static NEXT_LAYER_ID: AtomicU64 = AtomicU64::new(0);
 
 
-pub struct BushNodeItemIterLeftMut<'a, T> {
+impl<T> Layer<T> {
 
-    node: Option<&'a mut BushNode<T>>
+    fn new() -> Layer<T> {
+        Self {
+            first: null_node(),
+            last: null_node(),
+            parent: null_node(),
+            len: 0,
+            total: Cell::new(0),
+            total_valid: Cell::new(true),
+            id: NEXT_LAYER_ID.fetch_add(1, Ordering::Relaxed),
+            generation: 0,
+            forward: Cell::new(null_mut()),
+            retired: Vec::new(),
+        }
+    }
 
 }
 
 
-impl<'a, T> Iterator for BushNodeItemIterRight<'a, T> {
-    type Item = &'a T;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        if let Some(node) = self.node {
-            self.node = if node.right.is_null() {
-                None
-            } else {
-                unsafe {
-                    Some(&(*node.right))
-                }
-            };
-            Some(&node.item)
-        } else {
-            None
+impl<T> Drop for Layer<T> {
+    fn drop(&mut self) {
+        // Retired layers own the layers retired into them in turn, so they are freed from an explicit stack
+        let mut retired = std::mem::take(&mut self.retired);
+        while let Some(layer) = retired.pop() {
+            let mut layer = unsafe { Box::from_raw(layer.as_ptr()) };
+            retired.append(&mut layer.retired);
         }
     }
 }
 
 
-impl<'a, T> Iterator for BushNodeItemIterRightMut<'a, T> {
-    type Item = &'a mut T;
+/// Follow the forwarding links from a possibly retired layer to the live layer its nodes are in.
+/// Every layer on the way is pointed straight at the live one, so that later lookups are short.
+unsafe fn live_layer<T>(layer: *mut Layer<T>) -> *mut Layer<T> {
+    if layer.is_null() {
+        return layer;
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if let Some(node) = self.node.take() {
-            if !node.right.is_null() {
-                unsafe {
-                    self.node = Some(&mut (*node.right))
-                }
-            };
-            Some(&mut node.item)
-        } else {
-            None
-        }
+    let mut live = layer;
+    while !(*live).forward.get().is_null() {
+        live = (*live).forward.get();
+    }
+
+    let mut layer = layer;
+    while layer != live {
+        let next = (*layer).forward.get();
+        (*layer).forward.set(live);
+        layer = next;
     }
+    live
 }
 
 
-impl<'a, T> Iterator for BushNodeItemIterLeft<'a, T> {
-    type Item = &'a T;
+/// Get the live layer the node is in, null if the node is detached
+#[inline]
+unsafe fn node_layer<T>(node: *const BushNode<T>) -> *mut Layer<T> {
+    live_layer((*node).linked_layer)
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if let Some(node) = self.node {
-            self.node = if node.left.is_null() {
-                None
-            } else {
-                unsafe {
-                    Some(&(*node.left))
-                }
-            };
-            Some(&node.item)
-        } else {
-            None
+
+/// Invalidate the cached total of `layer` and of the layers above it.
+/// The walk stops at the first layer that is already invalid, since the layers above it are too.
+unsafe fn invalidate_total<T>(mut layer: *mut Layer<T>) {
+    while !layer.is_null() && (*layer).total_valid.get() {
+        (*layer).total_valid.set(false);
+        let parent = (*layer).parent;
+        if parent.is_null() {
+            break;
         }
+        layer = node_layer(parent);
     }
 }
 
 
-impl<'a, T> Iterator for BushNodeItemIterLeftMut<'a, T> {
-    type Item = &'a mut T;
+/// Get the number of nodes in `layer` and in every layer below it, recomputing the invalid cached totals.
+/// Only the invalid layers are visited, using an explicit stack so that deep bushes can't overflow the call stack.
+unsafe fn layer_total<T>(layer: *const Layer<T>) -> usize {
+    let mut layers = vec![layer];
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if let Some(node) = self.node.take() {
-            if !node.left.is_null() {
-                unsafe {
-                    self.node = Some(&mut (*node.left))
+    while let Some(&layer) = layers.last() {
+        if (*layer).total_valid.get() {
+            layers.pop();
+            continue;
+        }
+
+        let mut total = (*layer).len;
+        let mut pending = false;
+
+        let mut node = (*layer).first;
+        while !node.is_null() {
+            if let Some(children) = &(*node).children {
+                let children = children.layer.as_ptr();
+                if (*children).total_valid.get() {
+                    total += (*children).total.get();
+                } else {
+                    // Compute the children first, then come back to this layer
+                    layers.push(children);
+                    pending = true;
                 }
-            };
-            Some(&mut node.item)
-        } else {
-            None
+            }
+            node = (*node).right;
+        }
+
+        if !pending {
+            (*layer).total.set(total);
+            (*layer).total_valid.set(true);
+            layers.pop();
         }
     }
+
+    (*layer).total.get()
 }
 
 
-pub struct BushNodeIterLeft<'a, T> {
+/// Account for `count` nodes that were linked into `layer`
+unsafe fn nodes_added<T>(layer: *mut Layer<T>, count: usize) {
+    if !layer.is_null() {
+        (*layer).len += count;
+        invalidate_total(layer);
+    }
+}
 
-    node: Option<&'a BushNode<T>>
 
+/// Account for `count` nodes that were unlinked from `layer`
+unsafe fn nodes_removed<T>(layer: *mut Layer<T>, count: usize) {
+    if !layer.is_null() {
+        (*layer).len -= count;
+        invalidate_total(layer);
+        bump_generation(layer);
+    }
 }
 
 
-pub struct BushNodeIterLeftMut<'a, T> {
+/// Record that nodes were removed from `layer`, invalidating the checked handles of every bush containing it
+unsafe fn bump_generation<T>(mut layer: *mut Layer<T>) {
+    while !layer.is_null() {
+        (*layer).generation += 1;
+        let parent = (*layer).parent;
+        if parent.is_null() {
+            break;
+        }
+        layer = node_layer(parent);
+    }
+}
 
-    node: Option<&'a mut BushNode<T>>
 
+/// Set the owning layer of the run of nodes from `start` to `end`, following the right links.
+/// Return the number of nodes in the run.
+unsafe fn set_run_layer<T>(start: *mut BushNode<T>, end: *mut BushNode<T>, layer: *mut Layer<T>) -> usize {
+    let mut count = 1;
+    let mut node = start;
+    loop {
+        (*node).linked_layer = layer;
+        // A detached node is owned by a box, which must not be aliased by the back link of its children
+        if let Some(children) = &(*node).children {
+            (*children.layer.as_ptr()).parent = if layer.is_null() { null_node() } else { node };
+        }
+        if ptr::addr_eq(node, end) {
+            break;
+        }
+        node = (*node).right;
+        count += 1;
+    }
+    count
 }
 
 
-impl<'a, T> Iterator for BushNodeIterLeft<'a, T> {
-    type Item = &'a BushNode<T>;
+/// Link the detached run of nodes from `start` to `end` into `layer`, between `left` and `right`.
+/// A null `left` or `right` makes the run the first or last of the layer.
+unsafe fn link_run<T>(layer: *mut Layer<T>, left: *mut BushNode<T>, right: *mut BushNode<T>, start: *mut BushNode<T>, end: *mut BushNode<T>) {
+    let count = set_run_layer(start, end, layer);
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if let Some(node) = self.node {
-            self.node = if node.left.is_null() {
-                None
-            } else {
-                unsafe {
-                    Some(&(*node.left))
-                }
-            };
-            Some(node)
-        } else {
-            None
-        }
+    (*start).left = left;
+    (*end).right = right;
+
+    if !left.is_null() {
+        (*left).right = start;
+    } else if !layer.is_null() {
+        (*layer).first = start;
     }
+
+    if !right.is_null() {
+        (*right).left = end;
+    } else if !layer.is_null() {
+        (*layer).last = end;
+    }
+
+    nodes_added(layer, count);
 }
 
 
-impl<'a, T> Iterator for BushNodeIterLeftMut<'a, T> {
-    type Item = &'a mut BushNode<T>;
+/// Unlink the run of nodes from `start` to `end` from their layer, leaving them detached
+unsafe fn unlink_run<T>(start: *mut BushNode<T>, end: *mut BushNode<T>) {
+    let layer = node_layer(start);
+    let left = (*start).left;
+    let right = (*end).right;
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if let Some(node) = self.node.take() {
-            if !node.left.is_null() {
-                unsafe {
-                    self.node = Some(&mut (*node.left))
-                }
-            };
-            Some(node)
-        } else {
-            None
-        }
+    if !left.is_null() {
+        (*left).right = right;
+    } else if !layer.is_null() {
+        (*layer).first = right;
+    }
+
+    if !right.is_null() {
+        (*right).left = left;
+    } else if !layer.is_null() {
+        (*layer).last = left;
     }
+
+    (*start).left = null_node();
+    (*end).right = null_node();
+    let count = set_run_layer(start, end, null_mut());
+    nodes_removed(layer, count);
 }
 
 
-pub struct BushNodeIterRight<'a, T> {
+/// Link the whole top layer of `other` into `layer`, between `left` and `right`, without visiting its nodes.
+/// The layer of `other` is retired: it forwards to `layer`, which keeps it alive for the nodes still pointing to it.
+unsafe fn link_layer<T>(layer: *mut Layer<T>, left: *mut BushNode<T>, right: *mut BushNode<T>, other: Bush<T>) {
+    if other.is_empty() {
+        return;
+    }
 
-    node: Option<&'a BushNode<T>>
+    let other = std::mem::ManuallyDrop::new(other);
+    let retired = other.layer.as_ptr();
+    let start = (*retired).first;
+    let end = (*retired).last;
 
-}
+    (*start).left = left;
+    (*end).right = right;
 
+    if !left.is_null() {
+        (*left).right = start;
+    } else {
+        (*layer).first = start;
+    }
 
-pub struct BushNodeIterRightMut<'a, T> {
+    if !right.is_null() {
+        (*right).left = end;
+    } else {
+        (*layer).last = end;
+    }
 
-    node: Option<&'a mut BushNode<T>>
+    let count = (*retired).len;
+    (*retired).first = null_node();
+    (*retired).last = null_node();
+    (*retired).len = 0;
+    (*retired).forward.set(layer);
+    (*layer).retired.push(other.layer);
 
+    nodes_added(layer, count);
 }
 
 
-impl<'a, T> Iterator for BushNodeIterRight<'a, T> {
-    type Item = &'a BushNode<T>;
+/// Unlink the run of nodes from `start` to `end` and link it at the end of the children of `node`,
+/// giving the node children if it has none
+unsafe fn move_run_to_children<T>(node: *mut BushNode<T>, start: *mut BushNode<T>, end: *mut BushNode<T>) {
+    unlink_run(start, end);
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if let Some(node) = self.node {
-            self.node = if node.right.is_null() {
-                None
-            } else {
-                unsafe {
-                    Some(&(*node.right))
-                }
-            };
-            Some(node)
-        } else {
-            None
-        }
+    if (*node).children.is_none() {
+        (*node).set_children(Some(Bush::new()));
+    }
+    if let Some(children) = &(*node).children {
+        let layer = children.layer.as_ptr();
+        link_run(layer, (*layer).last, null_node(), start, end);
     }
 }
 
 
-impl<'a, T> Iterator for BushNodeIterRightMut<'a, T> {
-    type Item = &'a mut BushNode<T>;
+pub struct BushNodeAncestors<'a, T> {
+
+    node: Option<&'a BushNode<T>>
+
+}
+
+
+impl<'a, T> Iterator for BushNodeAncestors<'a, T> {
+    type Item = &'a BushNode<T>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if let Some(node) = self.node.take() {
-            if !node.right.is_null() {
-                unsafe {
-                    self.node = Some(&mut (*node.right))
-                }
-            };
-            Some(node)
-        } else {
-            None
-        }
+        self.node.inspect(|node| {
+            self.node = node.parent_node();
+        })
     }
 }
 
 
+/// A node of a bush, holding an item and optionally a children bush.
+///
+/// Nodes are linked to their siblings and to their children by address, so they must never move.
+/// The node is unsized to enforce that: it only ever lives behind a `Box` or a reference, and can't be
+/// moved out of either by safe code.
+///
+/// A mutable node never lends out its children, since a child reaches its parent through `parent_node`
+/// while the parent would still be mutably borrowed. Nested nodes are edited through the bush instead,
+/// by path, handle, cursor or mutable walk.
+///
+/// ```compile_fail
+/// use rs_bush::bush::BushNode;
+///
+/// fn unbox(node: Box<BushNode<i32>>) -> BushNode<i32> {
+///     *node
+/// }
+/// ```
+#[repr(C)]
 pub struct BushNode<T> {
 
     left: *mut BushNode<T>,
     right: *mut BushNode<T>,
 
-    pub children: Option<Bush<T>>,
+    /// The layer this node was linked into, null if the node is detached.
+    /// The layer may have been retired since, so it's only read through `node_layer`.
+    linked_layer: *mut Layer<T>,
+
+    children: Option<Bush<T>>,
+
+    pub item: T,
+
+    /// Zero-length tail that makes the node unsized
+    unsized_tail: [()]
+
+}
+
+
+/// The sized twin of `BushNode`, with the same layout minus the empty tail.
+/// Nodes are allocated as this type and then cast to `BushNode`.
+#[repr(C)]
+struct SizedNode<T> {
+
+    left: *mut BushNode<T>,
+    right: *mut BushNode<T>,
+    linked_layer: *mut Layer<T>,
+    children: Option<Bush<T>>,
+    item: T,
+
+}
 
-    pub item: T
 
+/// Get a null node pointer, since `null_mut` only works for sized types
+#[inline(always)]
+fn null_node<T>() -> *mut BushNode<T> {
+    node_from_sized(null_mut())
+}
+
+
+/// Turn a pointer to a sized node into a pointer to a `BushNode`, keeping its provenance
+#[inline(always)]
+fn node_from_sized<T>(node: *mut SizedNode<T>) -> *mut BushNode<T> {
+    ptr::slice_from_raw_parts_mut(node.cast::<()>(), 0) as *mut BushNode<T>
 }
 
 
 impl<T> BushNode<T> {
 
-    /// Create a new bush node
-    pub fn new(item: T, left: *mut BushNode<T>) -> BushNode<T> {
-        Self {
-            left,
-            right: null_mut(),
+    /// Create a new detached bush node
+    pub fn new(item: T) -> Box<BushNode<T>> {
+        let node = Box::into_raw(Box::new(SizedNode {
+            left: null_node(),
+            right: null_node(),
+            linked_layer: null_mut(),
             children: None,
             item
-        }
+        }));
+
+        // Same layout and alignment, so the box frees the allocation it was given
+        unsafe { Box::from_raw(node_from_sized(node)) }
+    }
+
+
+    /// Move the detached node out of its box as its sized twin, so that its fields can be taken apart
+    fn into_sized(self: Box<Self>) -> SizedNode<T> {
+        unsafe { *Box::from_raw(Box::into_raw(self) as *mut SizedNode<T>) }
     }
 
 
     pub fn into_handle(&self) -> NodeHandle<T> {
-        NodeHandle(self as *const BushNode<T>)
+        NodeHandle(self.link_ptr())
+    }
+
+
+    /// Get a pointer to this node with the same provenance as the links that point to it.
+    /// A pointer derived from a reference to the node would be invalidated by the next write through that reference,
+    /// so links must never be created from `self`.
+    fn link_ptr(&self) -> *mut BushNode<T> {
+        unsafe {
+            if !self.left.is_null() {
+                (*self.left).right
+            } else if !self.right.is_null() {
+                (*self.right).left
+            } else if !self.layer().is_null() {
+                (*self.layer()).first
+            } else {
+                // Detached node, nothing links to it yet
+                self as *const BushNode<T> as *mut BushNode<T>
+            }
+        }
+    }
+
+
+    /// Get the live layer this node is in, null if the node is detached
+    #[inline]
+    fn layer(&self) -> *mut Layer<T> {
+        unsafe { node_layer(self) }
+    }
+
+
+    /// Get the children of this node, if any
+    pub fn children(&self) -> Option<&Bush<T>> {
+        self.children.as_ref()
+    }
+
+
+    /// Set the children of this node, returning the previous ones.
+    /// The children of a detached node have no parent until the node is linked into a bush.
+    pub fn set_children(&mut self, children: Option<Bush<T>>) -> Option<Bush<T>> {
+        let old_children = self.take_children();
+
+        if let Some(children) = &children {
+            unsafe {
+                (*children.layer.as_ptr()).parent = if self.layer().is_null() { null_node() } else { self.link_ptr() };
+                invalidate_total(self.layer());
+            }
+        }
+        self.children = children;
+
+        old_children
+    }
+
+
+    /// Take the children of this node, leaving it without children
+    pub fn take_children(&mut self) -> Option<Bush<T>> {
+        let children = self.children.take();
+
+        if let Some(children) = &children {
+            unsafe {
+                (*children.layer.as_ptr()).parent = null_node();
+                invalidate_total(self.layer());
+                bump_generation(self.layer());
+            }
+        }
+
+        children
     }
 
 
-    /// Insert the given slice to the left while preserving the links
+    /// Append a new node to the children of this node, giving it children if it has none
+    pub fn append_child(&mut self, item: T) {
+        let children = self.children_layer();
+        let node = Box::into_raw(BushNode::new(item));
+        unsafe {
+            link_run(children, (*children).last, null_node(), node, node);
+        }
+    }
+
+
+    /// Prepend a new node to the children of this node, giving it children if it has none
+    pub fn prepend_child(&mut self, item: T) {
+        let children = self.children_layer();
+        let node = Box::into_raw(BushNode::new(item));
+        unsafe {
+            link_run(children, null_node(), (*children).first, node, node);
+        }
+    }
+
+
+    /// Get the layer of the children of this node, creating it if the node has no children.
+    /// The cached totals are invalidated up front, so that linking nodes into the layer doesn't need to
+    /// go through the parent link to this node, which would conflict with the mutable borrow of `self`.
+    fn children_layer(&mut self) -> *mut Layer<T> {
+        if self.children.is_none() {
+            self.set_children(Some(Bush::new()));
+        }
+        let children = self.children.as_ref().map_or(null_mut(), |children| children.layer.as_ptr());
+        unsafe {
+            (*children).total_valid.set(false);
+            invalidate_total(self.layer());
+        }
+        children
+    }
+
+
+    /// Insert the given slice to the left while preserving the links.
+    /// Panics if this node is detached, since its box only owns the node itself.
     pub fn insert_slice_left(&mut self, slice: BushSlice<T>) {
-        let start_node = Box::leak(slice.0);
-        let end_node = Box::leak(slice.1);
-        
-        if !self.left.is_null() {
+        assert!(!self.layer().is_null(), "cannot insert next to a detached node");
+        if let Some((start, end)) = slice.into_bush().take_run() {
             unsafe {
-                (*self.left).right = start_node;
+                self.insert_run_left(start, end);
+            }
+        }
+    }
+
+
+    /// Insert the given slice to the right while preserving the links.
+    /// Panics if this node is detached, since its box only owns the node itself.
+    pub fn insert_slice_right(&mut self, slice: BushSlice<T>) {
+        assert!(!self.layer().is_null(), "cannot insert next to a detached node");
+        if let Some((start, end)) = slice.into_bush().take_run() {
+            unsafe {
+                self.insert_run_right(start, end);
             }
         }
+    }
+
 
-        start_node.left = self.left;
-        end_node.right = self;
+    /// Insert the detached run of nodes from `start_node` to `end_node` to the left
+    unsafe fn insert_run_left(&mut self, start_node: *mut BushNode<T>, end_node: *mut BushNode<T>) {
+        assert!(!self.layer().is_null(), "cannot insert next to a detached node");
+        let this = self.link_ptr();
+
+        unsafe {
+            let count = set_run_layer(start_node, end_node, self.layer());
+
+            if !self.left.is_null() {
+                (*self.left).right = start_node;
+            } else if !self.layer().is_null() {
+                // This node was the first of its layer
+                (*self.layer()).first = start_node;
+            }
+
+            (*start_node).left = self.left;
+            (*end_node).right = this;
+
+            nodes_added(self.layer(), count);
+        }
 
         self.left = end_node;
     }
 
 
-    /// Insert the given slice to the right while preserving the links
-    pub fn insert_slice_right(&mut self, slice: BushSlice<T>) {
+    /// Insert the detached run of nodes from `start_node` to `end_node` to the right
+    unsafe fn insert_run_right(&mut self, start_node: *mut BushNode<T>, end_node: *mut BushNode<T>) {
+        assert!(!self.layer().is_null(), "cannot insert next to a detached node");
+        let this = self.link_ptr();
 
-        let start_node = Box::leak(slice.0);
-        let end_node = Box::leak(slice.1);
+        unsafe {
+            let count = set_run_layer(start_node, end_node, self.layer());
 
-        if !self.right.is_null() {
-            unsafe {
+            if !self.right.is_null() {
                 (*self.right).left = end_node;
+            } else if !self.layer().is_null() {
+                // This node was the last of its layer
+                (*self.layer()).last = end_node;
             }
-        }
 
-        end_node.right = self.right;
-        start_node.left = self;
+            (*end_node).right = self.right;
+            (*start_node).left = this;
+
+            nodes_added(self.layer(), count);
+        }
 
         self.right = start_node;
     }
 
 
-    /// Insert the given node to the left while preserving the links
+    /// Insert the given node to the left while preserving the links.
+    /// Panics if this node is detached, since its box only owns the node itself.
     pub fn insert_left_node(&mut self, node: Box<BushNode<T>>) {
-        let node = Box::leak(node);
+        assert!(!self.layer().is_null(), "cannot insert next to a detached node");
+        let node = Box::into_raw(node);
+        let this = self.link_ptr();
 
-        node.right = self;
+        unsafe {
+            let count = set_run_layer(node, node, self.layer());
+            (*node).right = this;
 
-        if !self.left.is_null() {
-            unsafe {
+            if !self.left.is_null() {
                 (*self.left).right = node;
+            } else if !self.layer().is_null() {
+                (*self.layer()).first = node;
             }
-        } 
 
-        node.left = self.left;
+            (*node).left = self.left;
+
+            nodes_added(self.layer(), count);
+        }
 
         self.left = node;
     }
 
 
-    /// Insert the given node to the right while preserving the links
+    /// Insert the given node to the right while preserving the links.
+    /// Panics if this node is detached, since its box only owns the node itself.
     pub fn insert_right_node(&mut self, node: Box<BushNode<T>>) {
-        let node = Box::leak(node);
+        assert!(!self.layer().is_null(), "cannot insert next to a detached node");
+        let node = Box::into_raw(node);
+        let this = self.link_ptr();
 
-        node.left = self;
+        unsafe {
+            let count = set_run_layer(node, node, self.layer());
+            (*node).left = this;
 
-        if !self.right.is_null() {
-            unsafe {
+            if !self.right.is_null() {
                 (*self.right).left = node;
+            } else if !self.layer().is_null() {
+                (*self.layer()).last = node;
             }
-        }
 
-        node.right = self.right;
+            (*node).right = self.right;
+
+            nodes_added(self.layer(), count);
+        }
 
         self.right = node;
     }
 
 
-    /// Get the node to the left, if any
-    pub fn left_node(&self) -> Option<&BushNode<T>> {
-        if self.left.is_null() {
+    /// Get the node whose children contain this node, if any
+    pub fn parent_node(&self) -> Option<&BushNode<T>> {
+        if self.layer().is_null() {
+            return None;
+        }
+
+        let parent = unsafe { (*self.layer()).parent };
+        if parent.is_null() {
             None
         } else {
             unsafe {
-                Some(&*self.left)
+                Some(&*parent)
             }
         }
     }
 
 
-    /// Get the node to the right, if any
-    pub fn right_node(&self) -> Option<&BushNode<T>> {
-        if self.right.is_null() {
-            None
-        } else {
-            unsafe {
-                Some(&*self.right)
-            }
+    /// Get the item of the parent node, if any
+    pub fn parent_item(&self) -> Option<&T> {
+        self.parent_node().map(|node| &node.item)
+    }
+
+
+    /// Get an iterator over the ancestors of this node, from its parent up to the root layer
+    pub fn ancestors(&self) -> BushNodeAncestors<'_, T> {
+        BushNodeAncestors {
+            node: self.parent_node()
         }
     }
 
 
+    /// Get an iterator over the items of the ancestors of this node, from its parent up to the root layer
+    pub fn ancestor_items(&self) -> IterItems<T, BushNodeAncestors<'_, T>> {
+        self.ancestors().map(|node| &node.item)
+    }
+
+
+    /// Return the number of ancestors of this node, 0 for a node in a bush's root layer
+    pub fn depth(&self) -> usize {
+        self.ancestors().count()
+    }
+
+
+    /// Get the ancestor of this node in the bush's root layer, or the node itself if it has no parent
+    pub fn root_of(&self) -> &BushNode<T> {
+        self.ancestors().last().unwrap_or(self)
+    }
+
+
+    /// Return the number of nodes in the subtree rooted at this node, including the node itself
+    pub fn subtree_size(&self) -> usize {
+        1 + self.children.as_ref().map_or(0, Bush::total_node_count)
+    }
+
+
+
     /// Get the node to the left, if any
-    pub fn left_node_mut(&self) -> Option<&mut BushNode<T>> {
+    pub fn left_node(&self) -> Option<&BushNode<T>> {
         if self.left.is_null() {
             None
         } else {
             unsafe {
-                Some(&mut *self.left)
+                Some(&*self.left)
             }
         }
     }
 
 
     /// Get the node to the right, if any
-    pub fn right_node_mut(&self) -> Option<&mut BushNode<T>> {
+    pub fn right_node(&self) -> Option<&BushNode<T>> {
         if self.right.is_null() {
             None
         } else {
             unsafe {
-                Some(&mut *self.right)
+                Some(&*self.right)
             }
         }
     }
@@ -416,69 +815,58 @@ impl<T> BushNode<T> {
 
 
     /// Get an iterator over the items to the left
-    pub fn iter_items_left(&self) -> IterItems<T, BushNodeIterLeft<T>> {
+    pub fn iter_items_left(&self) -> IterItems<T, BushNodeIterLeft<'_, T>> {
         self.iter_nodes_left().map(|node| &node.item)
     }
 
 
     /// Get an iterator over the items to the right
-    pub fn iter_items_right(&self) -> IterItems<T, BushNodeIterRight<T>> {
+    pub fn iter_items_right(&self) -> IterItems<T, BushNodeIterRight<'_, T>> {
         self.iter_nodes_right().map(|node| &node.item)
     }
 
 
+    /// Get an iterator over the mutable items to the left, starting from this node's own item.
+    /// Only the items are lent out, since a mutable node could relink the siblings yielded along with it.
+    pub fn iter_items_left_mut(&mut self) -> BushNodeItemIterLeftMut<'_, T> {
+        BushNodeItemIterLeftMut::new(self)
+    }
+
+
+    /// Get an iterator over the mutable items to the right, starting from this node's own item
+    pub fn iter_items_right_mut(&mut self) -> BushNodeItemIterRightMut<'_, T> {
+        BushNodeItemIterRightMut::new(self)
+    }
+
+
     /// Get an iterator over the nodes to the left
     pub fn iter_nodes_left(&self) -> BushNodeIterLeft<'_, T> {
-        BushNodeIterLeft { 
-            node: Some(self)
-        }
+        BushNodeIterLeft::new(self)
     }
 
 
     /// Get an iterator over the nodes to the right
     pub fn iter_nodes_right(&self) -> BushNodeIterRight<'_, T> {
-        BushNodeIterRight { 
-            node: Some(self)
-        }
+        BushNodeIterRight::new(self)
     }
 
 
-    pub fn bfs_nodes(&self) -> BFSIter<T> {
-        BFSIter {
-            nodes: self.children.as_ref().map(
-                |children|
-            {
-                let mut nodes = VecDeque::new();
-                if let Some(first_node) = children.first_node() {
-                    nodes.push_back(first_node);
-                }
-                nodes
-            }).unwrap_or_default()
-        }
+    pub fn bfs_nodes(&self) -> BFSIter<'_, T> {
+        BFSIter::new(self.children.as_ref().and_then(Bush::first_node))
     }
 
 
-    pub fn dfs_nodes(&self) -> DFSIter<T> {
-        DFSIter {
-            nodes: self.children.as_ref().map(
-                |children|
-            {
-                let mut nodes = VecDeque::new();
-                if let Some(first_node) = children.first_node() {
-                    nodes.push_back(first_node);
-                }
-                nodes
-            }).unwrap_or_default()
-        }
+    pub fn dfs_nodes(&self) -> DFSIter<'_, T> {
+        DFSIter::new(self.children.as_ref().and_then(Bush::first_node))
     }
 
 
-    pub fn bfs_items(&self) -> IterItems<T, BFSIter<T>> {
+    pub fn bfs_items(&self) -> IterItems<T, BFSIter<'_, T>> {
         self.bfs_nodes().map(|node| &node.item)
     }
 
 
-    pub fn dfs_items(&self) -> IterItems<T, DFSIter<T>> {
+    pub fn dfs_items(&self) -> IterItems<T, DFSIter<'_, T>> {
         self.dfs_nodes().map(|node| &node.item)
     }
 
@@ -487,8 +875,7 @@ impl<T> BushNode<T> {
 
 pub struct Bush<T> {
 
-    first: *mut BushNode<T>,
-    last: *mut BushNode<T>,
+    layer: NonNull<Layer<T>>,
 
 }
 
@@ -501,38 +888,68 @@ impl<T> Bush<T> {
     }
 
 
+    /// Create a bush whose top layer is the nodes of the slice
     pub fn from_slice(slice: BushSlice<T>) -> Bush<T> {
-        Bush {
-            first: Box::leak(slice.0),
-            last: Box::leak(slice.1),
+        slice.into_bush()
+    }
+
+
+    /// Create a bush whose top layer is the detached run of nodes from `first` to `last`
+    unsafe fn from_run(first: *mut BushNode<T>, last: *mut BushNode<T>) -> Bush<T> {
+        let bush = Bush::new();
+        link_run(bush.layer.as_ptr(), null_node(), null_node(), first, last);
+        bush
+    }
+
+
+    /// Detach the whole top layer, returning its first and last node, if any
+    fn take_run(&mut self) -> Option<(*mut BushNode<T>, *mut BushNode<T>)> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let layer = self.layer_mut();
+        let first = layer.first;
+        let last = layer.last;
+        layer.first = null_node();
+        layer.last = null_node();
+
+        unsafe {
+            let count = set_run_layer(first, last, null_mut());
+            nodes_removed(self.layer.as_ptr(), count);
         }
+
+        Some((first, last))
+    }
+
+
+    #[inline(always)]
+    fn layer(&self) -> &Layer<T> {
+        unsafe { self.layer.as_ref() }
+    }
+
+
+    #[inline(always)]
+    fn layer_mut(&mut self) -> &mut Layer<T> {
+        unsafe { self.layer.as_mut() }
     }
 
 
     pub fn is_empty(&self) -> bool {
-        self.first.is_null()
+        self.layer().first.is_null()
     }
 
 
     /// Return the number of nodes in the bush's top layer
     pub fn top_layer_length(&self) -> usize {
-        self.iter_nodes().count()
+        self.layer().len
     }
 
 
-    /// Return the total number of nodes in the bush
+    /// Return the total number of nodes in the bush.
+    /// The count is cached and only the layers edited since the last call are counted again.
     pub fn total_node_count(&self) -> usize {
-        self.iter_nodes().map(
-            |node| {
-                let mut count = 1;
-
-                if let Some(children) = &node.children {
-                    count += children.total_node_count();
-                }
-
-                count
-            }
-        ).sum()
+        unsafe { layer_total(self.layer.as_ptr()) }
     }
 
 
@@ -543,30 +960,32 @@ impl<T> Bush<T> {
 
 
     /// Get the last item of the bush's top layer
-    pub fn last_item_mut(&self) -> Option<&mut T> {
+    pub fn last_item_mut(&mut self) -> Option<&mut T> {
         self.last_node_mut().map(|node| &mut node.item)
     }
 
 
     /// Get the last node if the bush's top layer
     pub fn last_node(&self) -> Option<&BushNode<T>> {
-        if self.last.is_null() {
+        let last = self.layer().last;
+        if last.is_null() {
             None
         } else {
             unsafe {
-                Some(&*self.last)
+                Some(&*last)
             }
         }
     }
 
 
     /// Get the last node if the bush's top layer
-    pub fn last_node_mut(&self) -> Option<&mut BushNode<T>> {
-        if self.last.is_null() {
+    pub fn last_node_mut(&mut self) -> Option<&mut BushNode<T>> {
+        let last = self.layer().last;
+        if last.is_null() {
             None
         } else {
             unsafe {
-                Some(&mut *self.last)
+                Some(&mut *last)
             }
         }
     }
@@ -579,48 +998,112 @@ impl<T> Bush<T> {
 
 
     /// Get the first item of the bush's top layer
-    pub fn first_item_mut(&self) -> Option<&mut T> {
+    pub fn first_item_mut(&mut self) -> Option<&mut T> {
         self.first_node_mut().map(|node| &mut node.item)
     }
 
 
     pub fn first_node_handle(&self) -> Option<NodeHandle<T>> {
-        if self.first.is_null() {
+        let first = self.layer().first;
+        if first.is_null() {
             None
         } else {
-            Some(NodeHandle(self.first))
+            Some(NodeHandle(first))
         }
     }
 
 
     pub fn last_node_handle(&self) -> Option<NodeHandle<T>> {
-        if self.last.is_null() {
+        let last = self.layer().last;
+        if last.is_null() {
             None
         } else {
-            Some(NodeHandle(self.last))
+            Some(NodeHandle(last))
+        }
+    }
+
+
+    /// Get the node the given handle refers to. See `try_get` for a checked access.
+    ///
+    /// # Safety
+    ///
+    /// The handle must refer to a node of this bush, at any depth, that has not been removed.
+    pub unsafe fn node(&self, handle: NodeHandle<T>) -> &BushNode<T> {
+        debug_assert!(self.owns_node(handle.as_ptr()), "the node handle does not belong to this bush");
+        &*handle.as_ptr()
+    }
+
+
+    /// Get the node the given handle refers to. See `try_get_mut` for a checked access.
+    ///
+    /// # Safety
+    ///
+    /// The handle must refer to a node of this bush, at any depth, that has not been removed.
+    pub unsafe fn node_mut(&mut self, handle: NodeHandle<T>) -> &mut BushNode<T> {
+        debug_assert!(self.owns_node(handle.as_ptr()), "the node handle does not belong to this bush");
+        &mut *(handle.as_ptr() as *mut BushNode<T>)
+    }
+
+
+    /// Get the item of the node the given handle refers to
+    ///
+    /// # Safety
+    ///
+    /// Same as `node`.
+    pub unsafe fn item(&self, handle: NodeHandle<T>) -> &T {
+        &self.node(handle).item
+    }
+
+
+    /// Get the item of the node the given handle refers to
+    ///
+    /// # Safety
+    ///
+    /// Same as `node_mut`.
+    pub unsafe fn item_mut(&mut self, handle: NodeHandle<T>) -> &mut T {
+        &mut self.node_mut(handle).item
+    }
+
+
+    /// Return whether the given node is in this bush, at any depth.
+    /// The node must not have been dropped, but it may be detached or in another bush.
+    unsafe fn owns_node(&self, node: *const BushNode<T>) -> bool {
+        let mut layer = node_layer(node);
+        while !layer.is_null() {
+            if layer == self.layer.as_ptr() {
+                return true;
+            }
+            let parent = (*layer).parent;
+            if parent.is_null() {
+                return false;
+            }
+            layer = node_layer(parent);
         }
+        false
     }
 
 
     /// Get the first node of the bush's top layer
     pub fn first_node(&self) -> Option<&BushNode<T>> {
-        if self.first.is_null() {
+        let first = self.layer().first;
+        if first.is_null() {
             None
         } else {
             unsafe {
-                Some(&*self.first)
+                Some(&*first)
             }
         }
     }
 
 
     /// Get the first node of the bush's top layer
-    pub fn first_node_mut(&self) -> Option<&mut BushNode<T>> {
-        if self.first.is_null() {
+    pub fn first_node_mut(&mut self) -> Option<&mut BushNode<T>> {
+        let first = self.layer().first;
+        if first.is_null() {
             None
         } else {
             unsafe {
-                Some(&mut *self.first)
+                Some(&mut *first)
             }
         }
     }
@@ -640,217 +1123,166 @@ impl<T> Bush<T> {
 
     /// Append a new node to the bush's top layer
     pub fn append(&mut self, item: T) {
+        self.append_node(BushNode::new(item));
+    }
 
-        let node = Box::leak(Box::new(BushNode::new(item, self.last)));
 
-        if self.last.is_null() {
-            // Bush is empty
-            self.first = node;
-            self.last = node;
-        } else {
-            // Bush is not empty
-            unsafe {
-                (*self.last).right = node;
-            }
-            self.last = node;
+    /// Append the given node to the bush's top layer, preserving its branches
+    pub fn append_node(&mut self, node: Box<BushNode<T>>) {
+        let node = Box::into_raw(node);
+        unsafe {
+            link_run(self.layer.as_ptr(), self.layer().last, null_node(), node, node);
         }
     }
 
 
     /// Prepend a new node to the bush's top layer
     pub fn prepend(&mut self, item: T) {
+        self.prepend_node(BushNode::new(item));
+    }
 
-        let node = Box::leak(Box::new(BushNode::new(item, null_mut())));
 
-        if self.first.is_null() {
-            // Bush is empty
-            self.first = node;
-            self.last = node;
-        } else {
-            // Bush is not empty
-            unsafe {
-                (*self.first).left = node;
-            }
-            node.right = self.first;
-            self.first = node;
+    /// Prepend the given node to the bush's top layer, preserving its branches
+    pub fn prepend_node(&mut self, node: Box<BushNode<T>>) {
+        let node = Box::into_raw(node);
+        unsafe {
+            link_run(self.layer.as_ptr(), null_node(), self.layer().first, node, node);
         }
     }
 
 
     /// Get an iterator over the items of the bush's top layer
-    pub fn iter_items(&self) -> IterItems<T, BushNodeIterRight<T>> {
+    pub fn iter_items(&self) -> IterItems<T, BushLayerIter<'_, T>> {
         self.iter_nodes().map(|node| &node.item)
     }
 
 
     /// Get an iterator over the items of the bush's top layer
-    pub fn iter_items_mut(&mut self) -> IterItemsMut<T, BushNodeIterRightMut<T>> {
-        self.iter_nodes_mut().map(|node| &mut node.item)
+    pub fn iter_items_mut(&mut self) -> BushLayerItemIterMut<'_, T> {
+        unsafe { BushLayerItemIterMut::new(self.layer.as_ptr()) }
     }
 
 
     /// Get an iterator over the nodes of the bush's top layer
-    pub fn iter_nodes(&self) -> BushNodeIterRight<'_, T> {
-        BushNodeIterRight {
-            node: if self.first.is_null() {
-                None
-            } else {
-                Some(unsafe { &*self.first })
-            }
-        }
-    }
-
-
-    /// Get an iterator over the nodes of the bush's top layer
-    pub fn iter_nodes_mut(&self) -> BushNodeIterRightMut<'_, T> {
-        BushNodeIterRightMut {
-            node: if self.first.is_null() {
-                None
-            } else {
-                Some(unsafe { &mut *self.first })
-            }
-        }
-    }
-
-
-    /// Extrat the given node and its branches, assuming that the node is in the bush's top layer
-    /// Assumes the node pointer is not null
-    pub fn extract_node(&mut self, node: NodeHandle<T>) -> Box<BushNode<T>> {
-        let node_ptr = node.as_ptr() as *mut BushNode<T>;
-        let node = node.as_ref();
-
-        if node_ptr != self.first {
-            let left_node = node.left;
-            // Assume the left node is not null since the current node is not the first
-            unsafe {
-                (*left_node).right = node.right
-            }
-        } else {
-            self.first = node.right;
-        }
+    pub fn iter_nodes(&self) -> BushLayerIter<'_, T> {
+        unsafe { BushLayerIter::new(self.layer.as_ptr()) }
+    }
 
-        if node_ptr != self.last {
-            let right_node = node.right;
-            // Assume the right node is not null since the current node is not the last
-            unsafe {
-                (*right_node).left = node.left;
-            }
-        } else {
-            self.last = node.left;
-        }
 
-        unsafe { Box::from_raw(node_ptr) }
+    /// Get an iterator over the nodes of the bush's top layer, from right to left
+    pub fn iter_nodes_rev(&self) -> Rev<BushLayerIter<'_, T>> {
+        self.iter_nodes().rev()
     }
 
 
-    /// Extract a slice of the bush and the relative branches into a new bush, assumimg the nodes are n the bush's top layer
-    pub fn extract_slice(&mut self, start_node: NodeHandle<T>, end_node: NodeHandle<T>) -> BushSlice<T> {
-        let start_ptr = start_node.as_ptr() as *mut BushNode<T>;
-        let end_ptr = end_node.as_ptr() as *mut BushNode<T>;
-        let start_node = start_node.as_ref();
-        let end_node = end_node.as_ref();
+    /// Walk the nodes of the bush's top layer mutably, one node at a time
+    pub fn iter_nodes_mut(&mut self) -> BushLayerIterMut<'_, T> {
+        unsafe { BushLayerIterMut::new(self.layer.as_ptr()) }
+    }
 
-        if start_ptr != self.first {
-            let left_node = start_node.left;
-            // Assume the left node is not null
-            unsafe {
-                (*left_node).right = end_node.right;
-            }
-        } else {
-            self.first = end_node.right;
-        }
 
-        if end_ptr != self.last {
-            let right_node = end_node.right;
-            // Assume the right node is nt null
-            unsafe {
-                (*right_node).left = start_node.left;
-            }
-        } else {
-            self.last = start_node.left;
-        }
+    /// Extrat the given node and its branches from the bush's top layer.
+    /// Use `remove_node` for a node at any depth, or `try_extract_node` for a checked handle.
+    ///
+    /// # Safety
+    ///
+    /// The handle must refer to a node of the bush's top layer that has not been removed.
+    pub unsafe fn extract_node(&mut self, node: NodeHandle<T>) -> Box<BushNode<T>> {
+        let node_ptr = node.as_ptr() as *mut BushNode<T>;
+        debug_assert!(ptr::eq(node_layer(node_ptr), self.layer.as_ptr()), "the node is not in the bush's top layer");
 
-        unsafe {
-            BushSlice(Box::from_raw(start_ptr), Box::from_raw(end_ptr))
-        }
+        unlink_run(node_ptr, node_ptr);
+        Box::from_raw(node_ptr)
     }
 
 
-    /// Recursively flatten the bush into the top layer
-    pub fn flatten(&mut self) {
-
-        for node in self.iter_nodes_mut() {
-            if let Some(mut children) = node.children.take() {
-                children.flatten();
-                if let Some(children) = children.as_slice() {
-                    node.insert_slice_right(children);
-                }
-            }
-        }
+    /// Extract a slice of the bush and the relative branches into a new bush.
+    /// Use `remove_slice` for nodes at any depth, or `try_extract_slice` for checked handles.
+    ///
+    /// # Safety
+    ///
+    /// Both handles must refer to nodes of the bush's top layer that have not been removed,
+    /// and `end_node` must be `start_node` or one of the nodes to its right.
+    pub unsafe fn extract_slice(&mut self, start_node: NodeHandle<T>, end_node: NodeHandle<T>) -> BushSlice<T> {
+        let start_ptr = start_node.as_ptr() as *mut BushNode<T>;
+        let end_ptr = end_node.as_ptr() as *mut BushNode<T>;
+        debug_assert!(ptr::eq(node_layer(start_ptr), self.layer.as_ptr()), "the slice is not in the bush's top layer");
 
+        unlink_run(start_ptr, end_ptr);
+        BushSlice::from_bush(Bush::from_run(start_ptr, end_ptr))
     }
 
 
-    /// Return the first and last node of the bush's top layer, consuming the bush
-    pub fn as_slice(mut self) -> Option<BushSlice<T>> {
-        if self.first.is_null() {
+    /// Turn the bush's top layer into a slice, or None if the bush is empty
+    pub fn as_slice(self) -> Option<BushSlice<T>> {
+        if self.is_empty() {
             None
         } else {
-            // Set the first and last nodes to null to avoid dropping them when the bush is dropped
-            let first = self.first;
-            let last = self.last;
-            self.first = null_mut();
-            self.last = null_mut();
-            unsafe {
-                Some(BushSlice(Box::from_raw(first), Box::from_raw(last)))
-            }
+            Some(BushSlice::from_bush(self))
         }
     }
 
 
     /// Get a breadth first search iterator over the bush
-    pub fn bfs_nodes(&self) -> BFSIter<T> {
-        BFSIter {
-            nodes: if let Some(first) = self.first_node() {
-                VecDeque::from(vec![first])
-            } else {
-                VecDeque::new()
-            }
-        }
+    pub fn bfs_nodes(&self) -> BFSIter<'_, T> {
+        BFSIter::new(self.first_node())
     }
 
 
     /// Get a depth first search iterator over the bush
-    pub fn dfs_nodes(&self) -> DFSIter<T> {
-        DFSIter {
-            nodes: if let Some(first) = self.first_node() {
-                VecDeque::from(vec![first])
-            } else {
-                VecDeque::new()
-            }
-        }
+    pub fn dfs_nodes(&self) -> DFSIter<'_, T> {
+        DFSIter::new(self.first_node())
     }
 
 
     /// Get a breadth first search iterator over the bush
-    pub fn bfs_items(&self) -> IterItems<T, BFSIter<T>> {
+    pub fn bfs_items(&self) -> IterItems<T, BFSIter<'_, T>> {
         self.bfs_nodes().map(|node| &node.item)
     }
 
 
     /// Get a depth first search iterator over the bush
-    pub fn dfs_items(&self) -> IterItems<T, DFSIter<T>> {
+    pub fn dfs_items(&self) -> IterItems<T, DFSIter<'_, T>> {
         self.dfs_nodes().map(|node| &node.item)
     }
 
 }
 
 
+impl<T> Clone for Bush<T>
+where
+    T: Clone
+{
+    fn clone(&self) -> Self {
+        let clone = Bush::new();
+
+        // Layers left to copy, paired with the layer to copy them into
+        let mut layers = vec![(self, clone.layer.as_ptr())];
+
+        while let Some((source, target)) = layers.pop() {
+            for node in source.iter_nodes() {
+                let copy = Box::into_raw(BushNode::new(node.item.clone()));
+                unsafe {
+                    link_run(target, (*target).last, null_node(), copy, copy);
+
+                    if let Some(children) = &node.children {
+                        let children_copy = Bush::new();
+                        layers.push((children, children_copy.layer.as_ptr()));
+                        (*copy).set_children(Some(children_copy));
+                    }
+                }
+            }
+        }
+
+        clone
+    }
+}
+
+
 impl<T> Default for Bush<T> {
     fn default() -> Self {
         Self {
-            first: null_mut(),
-            last: null_mut(),
+            layer: NonNull::from(Box::leak(Box::new(Layer::new()))),
         }
     }
 }
@@ -858,7 +1290,40 @@ impl<T> Default for Bush<T> {
 
 pub struct BFSIter<'a, T> {
 
-    nodes: VecDeque<&'a BushNode<T>>,
+    /// Nodes left to visit, paired with their depth
+    nodes: VecDeque<(usize, &'a BushNode<T>)>,
+
+    min_depth: usize,
+    max_depth: usize,
+
+}
+
+
+impl<'a, T> BFSIter<'a, T> {
+
+    fn new(first: Option<&'a BushNode<T>>) -> Self {
+        Self {
+            nodes: first.map(|node| (0, node)).into_iter().collect(),
+            min_depth: 0,
+            max_depth: usize::MAX,
+        }
+    }
+
+
+    /// Skip the nodes above the given depth, which are still walked through.
+    /// The first layer of the traversal has depth 0.
+    pub fn min_depth(mut self, depth: usize) -> Self {
+        self.min_depth = depth;
+        self
+    }
+
+
+    /// Don't descend below the given depth, so that deeper layers are never visited.
+    /// The first layer of the traversal has depth 0.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
 
 }
 
@@ -867,31 +1332,65 @@ impl<'a, T> Iterator for BFSIter<'a, T> {
     type Item = &'a BushNode<T>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        
-        self.nodes.pop_front().map(
-            |node|
-        {
+        loop {
+            let (depth, node) = self.nodes.pop_front()?;
+
             // Push the same-layer node on the front to give it priority
-            if let Some(node) = node.right_node() {
-                self.nodes.push_front(node);
+            if let Some(right) = node.right_node() {
+                self.nodes.push_front((depth, right));
             }
 
             // Push lower-layer nodes on the back for lower priority
-            if let Some(children) = &node.children {
-                if let Some(first_node) = children.first_node() {
-                    self.nodes.push_back(first_node);
+            if depth < self.max_depth {
+                if let Some(first_node) = node.children.as_ref().and_then(Bush::first_node) {
+                    self.nodes.push_back((depth + 1, first_node));
                 }
             }
 
-            node
-        })
+            if depth >= self.min_depth {
+                return Some(node);
+            }
+        }
     }
 }
 
 
 pub struct DFSIter<'a, T> {
 
-    nodes: VecDeque<&'a BushNode<T>>,
+    /// Nodes left to visit, paired with their depth
+    nodes: VecDeque<(usize, &'a BushNode<T>)>,
+
+    min_depth: usize,
+    max_depth: usize,
+
+}
+
+
+impl<'a, T> DFSIter<'a, T> {
+
+    fn new(first: Option<&'a BushNode<T>>) -> Self {
+        Self {
+            nodes: first.map(|node| (0, node)).into_iter().collect(),
+            min_depth: 0,
+            max_depth: usize::MAX,
+        }
+    }
+
+
+    /// Skip the nodes above the given depth, which are still walked through.
+    /// The first layer of the traversal has depth 0.
+    pub fn min_depth(mut self, depth: usize) -> Self {
+        self.min_depth = depth;
+        self
+    }
+
+
+    /// Don't descend below the given depth, so that deeper layers are never visited.
+    /// The first layer of the traversal has depth 0.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
 
 }
 
@@ -900,35 +1399,51 @@ impl<'a, T> Iterator for DFSIter<'a, T> {
     type Item = &'a BushNode<T>;
 
     fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (depth, node) = self.nodes.pop_front()?;
 
-        self.nodes.pop_front().map(
-            |node|
-        {
             // Push the same-layer nodes before children nodes to give children priority
             if let Some(right) = node.right_node() {
-                self.nodes.push_front(right);
+                self.nodes.push_front((depth, right));
             }
 
-            if let Some(children) = &node.children {
-                if let Some(first_node) = children.first_node() {
-                    self.nodes.push_front(first_node);
+            if depth < self.max_depth {
+                if let Some(first_node) = node.children.as_ref().and_then(Bush::first_node) {
+                    self.nodes.push_front((depth + 1, first_node));
                 }
             }
 
-            node
-        })
+            if depth >= self.min_depth {
+                return Some(node);
+            }
+        }
     }
 }
 
 
 impl<T> Drop for Bush<T> {
     fn drop(&mut self) {
-        let mut node = self.first;
+        // Free nested layers from an explicit stack instead of letting each node drop its children recursively
+        let mut layers = vec![self.layer().first];
+
+        while let Some(mut node) = layers.pop() {
+            while !node.is_null() {
+                let mut owned_node = unsafe { Box::from_raw(node) };
+                node = owned_node.right;
+
+                if let Some(mut children) = owned_node.children.take() {
+                    // Detach the nodes so that dropping the children only frees their layer
+                    let layer = children.layer_mut();
+                    layers.push(layer.first);
+                    layer.first = null_node();
+                    layer.last = null_node();
+                }
+            }
+        }
 
-        while !node.is_null() {
-            let owned_node = unsafe { Box::from_raw(node) };
-            node = owned_node.right;
-        }        
+        unsafe {
+            drop(Box::from_raw(self.layer.as_ptr()));
+        }
     }
 }
 
@@ -936,6 +1451,7 @@ impl<T> Drop for Bush<T> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::{self, chain};
 
     #[test]
     fn create_empty_bush() {
@@ -1004,10 +1520,12 @@ mod tests {
         bush.append(2);
         bush.append(3);
         let mut iter = bush.iter_nodes_mut();
-        assert_eq!(iter.next().map(|n| n.item), Some(1));
-        assert_eq!(iter.next().map(|n| n.item), Some(2));
-        assert_eq!(iter.next().map(|n| n.item), Some(3));
-        assert_eq!(iter.next().map(|n| n.item), None);
+        assert_eq!(iter.len(), 3);
+        assert_eq!(iter.next_node().map(|n| n.item), Some(1));
+        assert_eq!(iter.next_node_back().map(|n| n.item), Some(3));
+        assert_eq!(iter.next_node().map(|n| n.item), Some(2));
+        assert!(iter.is_empty());
+        assert_eq!(iter.next_node().map(|n| n.item), None);
     }
 
 
@@ -1017,7 +1535,7 @@ mod tests {
         bush.append(1);
         bush.append(2);
         bush.append(3);
-        let node = bush.extract_node(bush.first_node_handle().unwrap());
+        let node = unsafe { bush.extract_node(bush.first_node_handle().unwrap()) };
         assert_eq!(node.item, 1);
         assert_eq!(bush.top_layer_length(), 2);
         assert_eq!(bush.total_node_count(), 2);
@@ -1030,7 +1548,7 @@ mod tests {
         bush.append(1);
         bush.append(2);
         bush.append(3);
-        let slice = Bush::from_slice(bush.extract_slice(bush.first_node_handle().unwrap(), bush.last_node_handle().unwrap()));
+        let slice = Bush::from_slice(unsafe { bush.extract_slice(bush.first_node_handle().unwrap(), bush.last_node_handle().unwrap()) });
         assert_eq!(slice.top_layer_length(), 3);
         assert_eq!(bush.top_layer_length(), 0);
         assert_eq!(slice.total_node_count(), 3);
@@ -1054,7 +1572,7 @@ mod tests {
                 counter += 1;
             }
             
-            bush.last_node_mut().unwrap().children = Some(children);
+            bush.last_node_mut().unwrap().set_children(Some(children));
         }
 
         assert_eq!(bush.total_node_count(), counter);
@@ -1077,7 +1595,7 @@ mod tests {
                 counter += 1;
             }
             
-            bush.last_node_mut().unwrap().children = Some(children);
+            bush.last_node_mut().unwrap().set_children(Some(children));
         }
 
         assert_eq!(bush.total_node_count(), counter);
@@ -1107,14 +1625,437 @@ mod tests {
                 counter += 1;
             }
 
-            bush.last_node_mut().unwrap().children = Some(children);
+            bush.last_node_mut().unwrap().set_children(Some(children));
         }
 
         let _extracted = bush.as_slice().unwrap();
 
     }
 
-    
 
-}
+    #[test]
+    fn parent_links() {
+        let mut bush: Bush<usize> = Bush::new();
+        bush.append(0);
+
+        let mut children = Bush::new();
+        children.append(1);
+        children.append(2);
+
+        let mut grandchildren = Bush::new();
+        grandchildren.append(3);
+        children.last_node_mut().unwrap().set_children(Some(grandchildren));
+
+        bush.first_node_mut().unwrap().set_children(Some(children));
+
+        let root = bush.first_node().unwrap();
+        assert!(root.parent_node().is_none());
+        assert_eq!(root.depth(), 0);
+
+        let child = root.children().unwrap().last_node().unwrap();
+        assert_eq!(child.parent_item(), Some(&0));
+        assert_eq!(child.depth(), 1);
+
+        let grandchild = child.children().unwrap().first_node().unwrap();
+        assert_eq!(grandchild.parent_item(), Some(&2));
+        assert_eq!(grandchild.ancestor_items().copied().collect::<Vec<_>>(), vec![2, 0]);
+        assert_eq!(grandchild.depth(), 2);
+        assert_eq!(grandchild.root_of().item, 0);
+        assert_eq!(root.root_of().item, 0);
+    }
+
+
+    #[test]
+    fn parent_links_after_flatten() {
+        let mut bush: Bush<usize> = Bush::new();
+        bush.append(0);
+
+        let mut children = Bush::new();
+        children.append(1);
+        children.append(2);
+        bush.first_node_mut().unwrap().set_children(Some(children));
+
+        bush.flatten();
+
+        assert_eq!(bush.last_item(), Some(&2));
+        for node in bush.iter_nodes() {
+            assert!(node.parent_node().is_none());
+            assert_eq!(node.depth(), 0);
+        }
+    }
+
+
+    #[test]
+    fn parent_links_after_move() {
+        let mut bush: Bush<usize> = Bush::new();
+        bush.append(0);
+        bush.append(1);
+
+        let mut children = Bush::new();
+        children.append(2);
+        bush.first_node_mut().unwrap().set_children(Some(children));
+
+        let node = unsafe { bush.extract_node(bush.last_node_handle().unwrap()) };
+        assert!(node.parent_node().is_none());
+
+        bush[&BushPath::from([0, 0])].insert_left_node(node);
+
+        // Inserting next to a nested node keeps the owning layer consistent
+        let children = bush.first_node().unwrap().children().unwrap();
+        assert_eq!(children.first_item(), Some(&1));
+        assert_eq!(children.top_layer_length(), 2);
+        for node in children.iter_nodes() {
+            assert_eq!(node.parent_item(), Some(&0));
+        }
+
+        let slice = unsafe { bush.extract_slice(bush.first_node_handle().unwrap(), bush.last_node_handle().unwrap()) };
+        let mut other = Bush::new();
+        other.append(10);
+        let mut nested = Bush::new();
+        nested.append(11);
+        other.first_node_mut().unwrap().set_children(Some(nested));
+
+        other[&BushPath::from([0, 0])].insert_slice_right(slice);
+
+        let nested = other.first_node().unwrap().children().unwrap();
+        assert_eq!(nested.last_item(), Some(&0));
+        assert_eq!(nested.last_node().unwrap().parent_item(), Some(&10));
+        let moved_child = nested.last_node().unwrap().children().unwrap().first_node().unwrap();
+        assert_eq!(moved_child.ancestor_items().copied().collect::<Vec<_>>(), vec![0, 10]);
+    }
+
+
+    #[test]
+    fn parent_links_of_moved_box() {
+        let mut bush: Bush<usize> = Bush::new();
+        bush.append(0);
+        bush.first_node_mut().unwrap().append_child(1);
+
+        // The box owns the detached node alone, so its children only link back to it once it's in a bush again
+        let node = unsafe { bush.extract_node(bush.first_node_handle().unwrap()) };
+        let mut boxes = vec![node];
+        let moved = boxes.pop().unwrap();
+        assert!(moved.children().unwrap().first_node().unwrap().parent_node().is_none());
+
+        let mut other = Bush::new();
+        other.append_node(moved);
+        assert_eq!(other.first_node().unwrap().children().unwrap().first_node().unwrap().depth(), 1);
+    }
+
+
+    #[test]
+    fn edit_children_through_node() {
+        let mut bush: Bush<usize> = Bush::new();
+        bush.append(0);
+        assert_eq!(bush.total_node_count(), 1);
+
+        let node = bush.first_node_mut().unwrap();
+        assert!(node.children().is_none());
+        node.append_child(2);
+        node.prepend_child(1);
+        node.append_child(3);
+        node.item = 5;
+        bush[&BushPath::from([0, 0])].item += 10;
+        bush[&BushPath::from([0, 2])].append_child(4);
+
+        assert_eq!(bush.dfs_items().copied().collect::<Vec<_>>(), vec![5, 11, 2, 3, 4]);
+        assert_eq!(bush.total_node_count(), 5);
+        let grandchild = bush.dfs_nodes().last().unwrap();
+        assert_eq!(grandchild.ancestor_items().copied().collect::<Vec<_>>(), vec![3, 5]);
+    }
+
+
+    #[test]
+    fn take_children() {
+        let mut bush: Bush<usize> = Bush::new();
+        bush.append(0);
+
+        let mut children = Bush::new();
+        children.append(1);
+        bush.first_node_mut().unwrap().set_children(Some(children));
+
+        let children = bush.first_node_mut().unwrap().take_children().unwrap();
+        assert!(bush.first_node().unwrap().children().is_none());
+        assert!(children.first_node().unwrap().parent_node().is_none());
+    }
+
+
+    // The following tests mix the raw links with mutable borrows: handles, mutable walks, sibling round trips
+    // through a cursor, and mutable nested nodes reading their parent. They are meant to be run under Miri as well:
+    // cargo +nightly miri test --all-features, which the CI runs on every push
+
+    #[test]
+    fn edit_through_handles() {
+        let mut bush = Bush::new();
+        for i in 0..4 {
+            bush.append(i);
+        }
+
+        let first = bush.first_node_handle().unwrap();
+        let last = bush.last_node_handle().unwrap();
+
+        *unsafe { bush.item_mut(first) } += 10;
+        unsafe { bush.node_mut(first) }.insert_right_node(BushNode::new(20));
+        unsafe { bush.node_mut(last) }.insert_left_node(BushNode::new(30));
+
+        assert_eq!(*unsafe { bush.item(first) }, 10);
+        assert_eq!(unsafe { bush.node(first) }.right_item(), Some(&20));
+        assert_eq!(bush.iter_items().copied().collect::<Vec<_>>(), vec![10, 20, 1, 2, 30, 3]);
+
+        let extracted = unsafe { bush.extract_node(first) };
+        assert_eq!(extracted.item, 10);
+        assert_eq!(bush.first_item(), Some(&20));
+        *unsafe { bush.item_mut(last) } = 40;
+        assert_eq!(bush.last_item(), Some(&40));
+    }
+
+
+    #[test]
+    fn edit_while_iterating() {
+        let mut bush = Bush::new();
+        for i in 0..4 {
+            bush.append(i);
+        }
+
+        let mut walk = bush.iter_nodes_mut();
+        while let Some(node) = walk.next_node() {
+            node.item *= 2;
+            let item = node.item + 1;
+            node.insert_right_node(BushNode::new(item));
+        }
+
+        assert_eq!(bush.iter_items().copied().collect::<Vec<_>>(), (0..8).collect::<Vec<_>>());
+        assert_eq!(bush.last_item(), Some(&7));
+
+        for item in bush.iter_items_mut() {
+            *item += 1;
+        }
+        assert_eq!(bush.first_item(), Some(&1));
+    }
+
+
+    #[test]
+    fn edit_siblings() {
+        let mut bush: Bush<i32> = (0..3).collect();
+
+        // The cursor lends one node at a time, so a round trip to a sibling and back can't alias the node it left
+        let mut cursor = bush.cursor_mut();
+        cursor.move_right();
+        cursor.current_node().unwrap().item = 15;
+        *cursor.peek_left().unwrap() = 10;
+        *cursor.peek_right().unwrap() = 20;
+        cursor.move_right();
+        cursor.move_left();
+        cursor.move_left();
+        *cursor.current().unwrap() += 1;
+        cursor.move_right();
+        cursor.current_node().unwrap().insert_right_node(BushNode::new(17));
+        assert_eq!(cursor.current(), Some(&mut 15));
+
+        *bush.first_item_mut().unwrap() += 1;
+        *bush.last_item_mut().unwrap() += 1;
+        assert_eq!(bush.iter_items().copied().collect::<Vec<_>>(), vec![12, 15, 17, 21]);
+    }
+
+
+    #[test]
+    fn edit_nested_through_handles() {
+        let mut bush = Bush::new();
+        bush.append(0);
+
+        let mut children = Bush::new();
+        children.append(1);
+        children.append(2);
+        bush.first_node_mut().unwrap().set_children(Some(children));
+
+        let child = bush.first_node().unwrap().children().unwrap().last_node().unwrap().into_handle();
+
+        let mut grandchildren = Bush::new();
+        grandchildren.append(3);
+        unsafe { bush.node_mut(child) }.set_children(Some(grandchildren));
+        unsafe { bush.node_mut(child) }.item = 20;
+        unsafe { bush.node_mut(child) }.insert_left_node(BushNode::new(15));
+
+        assert_eq!(unsafe { bush.node(child) }.parent_item(), Some(&0));
+        assert_eq!(unsafe { bush.node(child) }.children().unwrap().first_node().unwrap().ancestor_items().copied().collect::<Vec<_>>(), vec![20, 0]);
+        assert_eq!(bush.dfs_items().copied().collect::<Vec<_>>(), vec![0, 1, 15, 20, 3]);
+
+        bush.flatten();
+        assert_eq!(bush.iter_items().copied().collect::<Vec<_>>(), vec![0, 1, 15, 20, 3]);
+        assert_eq!(unsafe { bush.node(child) }.depth(), 0);
+    }
+
 
+    #[test]
+    fn edit_nested_then_parent() {
+        let mut bush: Bush<i32> = test_util::bush("0 [1, 2 [3]]");
+
+        // A mutable nested node reads its parent, then the parent is edited again
+        let child = &mut bush[&BushPath::from([0, 1])];
+        child.item += *child.parent_item().unwrap();
+        child.item *= 10;
+        bush[0].item = 5;
+
+        let mut walk = bush.dfs_nodes_mut();
+        while let Some(node) = walk.next_node() {
+            if let Some(&parent) = node.parent_item() {
+                node.item += parent;
+            }
+        }
+        bush[0].item += 1;
+        assert_eq!(bush.dfs_items().copied().collect::<Vec<_>>(), vec![6, 6, 25, 28]);
+
+        let mut cursor = bush.cursor_mut();
+        cursor.move_into_children();
+        cursor.move_right();
+        let parent = *cursor.current_node().unwrap().parent_item().unwrap();
+        *cursor.current().unwrap() -= parent;
+        cursor.move_to_parent();
+        *cursor.current().unwrap() += 1;
+        assert_eq!(bush.dfs_items().copied().collect::<Vec<_>>(), vec![7, 6, 19, 28]);
+    }
+
+
+    #[test]
+    fn edit_with_cursor_at_handle() {
+        let mut bush = Bush::new();
+        bush.append(0);
+
+        let mut children = Bush::new();
+        children.append(1);
+        children.append(2);
+        bush.first_node_mut().unwrap().set_children(Some(children));
+
+        let child = bush.first_node().unwrap().children().unwrap().first_node().unwrap().into_handle();
+
+        let mut cursor = unsafe { bush.cursor_mut_at(child) };
+        cursor.insert_before(-1);
+        *cursor.current().unwrap() = 10;
+        assert!(cursor.move_to_parent());
+        assert_eq!(cursor.current(), Some(&mut 0));
+
+        assert_eq!(unsafe { bush.cursor_at(child) }.current(), Some(&10));
+        assert_eq!(bush.dfs_items().copied().collect::<Vec<_>>(), vec![0, -1, 10, 2]);
+    }
+
+
+    #[test]
+    fn deep_chain() {
+        const DEPTH: usize = if cfg!(miri) { 1_000 } else { 1_000_000 };
+
+        let bush = chain(DEPTH);
+        assert_eq!(bush.total_node_count(), DEPTH);
+        assert_eq!(bush.dfs_items().last(), Some(&(DEPTH - 1)));
+
+        let mut clone = bush.clone();
+        assert_eq!(clone.total_node_count(), DEPTH);
+        assert!(clone.dfs_items().eq(bush.dfs_items()));
+
+        clone.flatten();
+        assert_eq!(clone.top_layer_length(), DEPTH);
+        assert!(clone.iter_items().copied().eq(0..DEPTH));
+
+        drop(bush);
+        drop(clone);
+    }
+
+
+    #[test]
+    fn cached_lengths() {
+        let mut bush = Bush::new();
+        for i in 0..4 {
+            bush.append(i);
+        }
+        bush.prepend(-1);
+        assert_eq!(bush.top_layer_length(), 5);
+        assert_eq!(bush.total_node_count(), 5);
+
+        let first = bush.first_node_handle().unwrap();
+        unsafe { bush.node_mut(first) }.insert_right_node(BushNode::new(10));
+        unsafe { bush.node_mut(first) }.insert_left_node(BushNode::new(11));
+        assert_eq!(bush.top_layer_length(), 7);
+
+        let node = bush.nth_node(2).unwrap().into_handle();
+        unsafe { bush.extract_node(node) };
+        assert_eq!(bush.top_layer_length(), 6);
+
+        let start = bush.nth_node(1).unwrap().into_handle();
+        let end = bush.nth_node(3).unwrap().into_handle();
+        let slice = unsafe { bush.extract_slice(start, end) };
+        assert_eq!(bush.top_layer_length(), 3);
+        assert_eq!(bush.total_node_count(), 3);
+
+        let other = Bush::from_slice(slice);
+        assert_eq!(other.top_layer_length(), 3);
+        assert_eq!(other.iter_nodes().count(), 3);
+    }
+
+
+    #[test]
+    fn cached_nested_totals() {
+        let mut bush = Bush::new();
+        bush.append(0);
+        bush.append(1);
+        assert_eq!(bush.total_node_count(), 2);
+
+        let mut children = Bush::new();
+        children.append(10);
+        children.append(11);
+        bush.first_node_mut().unwrap().set_children(Some(children));
+        assert_eq!(bush.total_node_count(), 4);
+        assert_eq!(bush.first_node().unwrap().subtree_size(), 3);
+        assert_eq!(bush.last_node().unwrap().subtree_size(), 1);
+
+        // Edits in a nested layer update the totals of the layers above it
+        bush.first_node_mut().unwrap().append_child(12);
+        let mut grandchildren = Bush::new();
+        grandchildren.append(20);
+        bush[&BushPath::from([0, 0])].set_children(Some(grandchildren));
+        assert_eq!(bush.total_node_count(), 6);
+        assert_eq!(bush.first_node().unwrap().subtree_size(), 5);
+        assert_eq!(bush.top_layer_length(), 2);
+
+        bush.remove_at_path(&BushPath::from([0, 0]));
+        assert_eq!(bush.total_node_count(), 4);
+
+        bush.first_node_mut().unwrap().take_children();
+        assert_eq!(bush.total_node_count(), 2);
+    }
+
+
+    #[test]
+    fn cached_lengths_after_flatten() {
+        let mut bush = chain(10);
+        assert_eq!(bush.top_layer_length(), 1);
+        assert_eq!(bush.total_node_count(), 10);
+
+        bush.flatten();
+        assert_eq!(bush.top_layer_length(), 10);
+        assert_eq!(bush.total_node_count(), 10);
+        assert!(bush.iter_nodes().all(|node| node.subtree_size() == 1));
+    }
+
+
+    #[test]
+    fn clone_keeps_structure() {
+        let mut bush: Bush<String> = Bush::new();
+        bush.append("a".to_string());
+        bush.append("b".to_string());
+
+        let mut children = Bush::new();
+        children.append("c".to_string());
+        children.append("d".to_string());
+        bush.first_node_mut().unwrap().set_children(Some(children));
+
+        let mut clone = bush.clone();
+        clone.first_item_mut().unwrap().push('!');
+
+        assert_eq!(bush.first_item().map(String::as_str), Some("a"));
+        assert_eq!(clone.dfs_items().map(String::as_str).collect::<Vec<_>>(), vec!["a!", "c", "d", "b"]);
+        let copied_child = clone.first_node().unwrap().children().unwrap().last_node().unwrap();
+        assert_eq!(copied_child.parent_item().map(String::as_str), Some("a!"));
+        assert_eq!(clone.first_node().unwrap().children().unwrap().last_item().map(String::as_str), Some("d"));
+    }
+
+
+}
@@ -0,0 +1,687 @@
+use super::{link_run, node_layer, unlink_run, Bush, BushNode, Layer, NodeHandle};
+
+
+/// A read-only cursor over a bush.
+///
+/// The cursor points either at a node or at the "ghost" position of its current layer, which sits
+/// between the last and the first node of the layer. Moving right from the last node or left from
+/// the first node lands on the ghost, and moving again wraps around.
+pub struct Cursor<'a, T> {
+
+    layer: *const Layer<T>,
+    current: *const BushNode<T>,
+
+    bush: &'a Bush<T>,
+
+}
+
+
+impl<T> Clone for Cursor<'_, T> {
+    fn clone(&self) -> Self {
+        Self {
+            layer: self.layer,
+            current: self.current,
+            bush: self.bush,
+        }
+    }
+}
+
+
+impl<'a, T> Cursor<'a, T> {
+
+    /// Get the item the cursor points to, or None if it's on the ghost position
+    pub fn current(&self) -> Option<&'a T> {
+        self.current_node().map(|node| &node.item)
+    }
+
+
+    /// Get the node the cursor points to, or None if it's on the ghost position
+    pub fn current_node(&self) -> Option<&'a BushNode<T>> {
+        if self.current.is_null() {
+            None
+        } else {
+            unsafe {
+                Some(&*self.current)
+            }
+        }
+    }
+
+
+    /// Get the item to the left of the cursor, wrapping around the ghost position
+    pub fn peek_left(&self) -> Option<&'a T> {
+        let node = unsafe { left_of(self.layer, self.current) };
+        if node.is_null() {
+            None
+        } else {
+            unsafe {
+                Some(&(*node).item)
+            }
+        }
+    }
+
+
+    /// Get the item to the right of the cursor, wrapping around the ghost position
+    pub fn peek_right(&self) -> Option<&'a T> {
+        let node = unsafe { right_of(self.layer, self.current) };
+        if node.is_null() {
+            None
+        } else {
+            unsafe {
+                Some(&(*node).item)
+            }
+        }
+    }
+
+
+    /// Move the cursor to the node to the left, or to the ghost position if on the first node
+    pub fn move_left(&mut self) {
+        self.current = unsafe { left_of(self.layer, self.current) };
+    }
+
+
+    /// Move the cursor to the node to the right, or to the ghost position if on the last node
+    pub fn move_right(&mut self) {
+        self.current = unsafe { right_of(self.layer, self.current) };
+    }
+
+
+    /// Move the cursor to the first child of the current node.
+    /// Return false, without moving, if the cursor is on the ghost position or the current node has no children.
+    /// If the children layer is empty, the cursor lands on its ghost position.
+    pub fn move_into_children(&mut self) -> bool {
+        match self.current_node().and_then(BushNode::children) {
+            Some(children) => {
+                self.layer = children.layer.as_ptr();
+                self.current = children.layer().first;
+                true
+            },
+            None => false
+        }
+    }
+
+
+    /// Move the cursor to the node whose children contain the current layer.
+    /// Return false, without moving, if the cursor is in the top layer of the bush it walks.
+    pub fn move_to_parent(&mut self) -> bool {
+        // The cursor must not leave the bush it borrows, which may itself be nested in another bush
+        if self.layer == self.bush.layer.as_ptr() {
+            return false;
+        }
+
+        let parent = unsafe { (*self.layer).parent };
+        if parent.is_null() {
+            false
+        } else {
+            self.current = parent;
+            self.layer = unsafe { node_layer(parent) };
+            true
+        }
+    }
+
+
+    /// Return the number of layers between the cursor and the top layer of the bush it walks
+    pub fn depth(&self) -> usize {
+        let top_layer = self.bush.layer.as_ptr() as *const Layer<T>;
+        let mut depth = 0;
+        let mut layer = self.layer;
+        while layer != top_layer {
+            layer = unsafe { node_layer((*layer).parent) };
+            depth += 1;
+        }
+        depth
+    }
+
+
+    /// Get the bush the cursor is walking
+    pub fn bush(&self) -> &'a Bush<T> {
+        self.bush
+    }
+
+}
+
+
+/// A cursor over a bush that can edit the layer it's in.
+///
+/// See `Cursor` for how the cursor moves. Every edit keeps the links of the moved nodes, and the
+/// first and last nodes of their layers, consistent.
+pub struct CursorMut<'a, T> {
+
+    layer: *mut Layer<T>,
+    current: *mut BushNode<T>,
+
+    bush: &'a mut Bush<T>,
+
+}
+
+
+impl<'a, T> CursorMut<'a, T> {
+
+    /// Get a read-only cursor at the same position, borrowing this cursor
+    pub fn as_cursor(&self) -> Cursor<'_, T> {
+        Cursor {
+            layer: self.layer,
+            current: self.current,
+            bush: self.bush,
+        }
+    }
+
+
+    /// Get the item the cursor points to, or None if it's on the ghost position
+    pub fn current(&mut self) -> Option<&mut T> {
+        self.current_node().map(|node| &mut node.item)
+    }
+
+
+    /// Get the node the cursor points to, or None if it's on the ghost position
+    pub fn current_node(&mut self) -> Option<&mut BushNode<T>> {
+        if self.current.is_null() {
+            None
+        } else {
+            unsafe {
+                Some(&mut *self.current)
+            }
+        }
+    }
+
+
+    /// Get the item to the left of the cursor, wrapping around the ghost position
+    pub fn peek_left(&mut self) -> Option<&mut T> {
+        let node = unsafe { left_of(self.layer, self.current) };
+        if node.is_null() {
+            None
+        } else {
+            unsafe {
+                Some(&mut (*node).item)
+            }
+        }
+    }
+
+
+    /// Get the item to the right of the cursor, wrapping around the ghost position
+    pub fn peek_right(&mut self) -> Option<&mut T> {
+        let node = unsafe { right_of(self.layer, self.current) };
+        if node.is_null() {
+            None
+        } else {
+            unsafe {
+                Some(&mut (*node).item)
+            }
+        }
+    }
+
+
+    /// Move the cursor to the node to the left, or to the ghost position if on the first node
+    pub fn move_left(&mut self) {
+        self.current = unsafe { left_of(self.layer, self.current) };
+    }
+
+
+    /// Move the cursor to the node to the right, or to the ghost position if on the last node
+    pub fn move_right(&mut self) {
+        self.current = unsafe { right_of(self.layer, self.current) };
+    }
+
+
+    /// Move the cursor to the first child of the current node.
+    /// Return false, without moving, if the cursor is on the ghost position or the current node has no children.
+    /// If the children layer is empty, the cursor lands on its ghost position.
+    pub fn move_into_children(&mut self) -> bool {
+        if self.current.is_null() {
+            return false;
+        }
+
+        match unsafe { &(*self.current).children } {
+            Some(children) => {
+                self.layer = children.layer.as_ptr();
+                self.current = unsafe { (*self.layer).first };
+                true
+            },
+            None => false
+        }
+    }
+
+
+    /// Move the cursor to the node whose children contain the current layer.
+    /// Return false, without moving, if the cursor is in the top layer of the bush it walks.
+    pub fn move_to_parent(&mut self) -> bool {
+        // The cursor must not leave the bush it borrows, which may itself be nested in another bush
+        if self.layer == self.bush.layer.as_ptr() {
+            return false;
+        }
+
+        let parent = unsafe { (*self.layer).parent };
+        if parent.is_null() {
+            false
+        } else {
+            self.current = parent;
+            self.layer = unsafe { node_layer(parent) };
+            true
+        }
+    }
+
+
+    /// Insert a new node to the left of the cursor.
+    /// If the cursor is on the ghost position, the node becomes the last of the layer.
+    pub fn insert_before(&mut self, item: T) {
+        self.insert_node_before(BushNode::new(item));
+    }
+
+
+    /// Insert a new node to the right of the cursor.
+    /// If the cursor is on the ghost position, the node becomes the first of the layer.
+    pub fn insert_after(&mut self, item: T) {
+        self.insert_node_after(BushNode::new(item));
+    }
+
+
+    /// Insert the given node and its branches to the left of the cursor.
+    /// If the cursor is on the ghost position, the node becomes the last of the layer.
+    pub fn insert_node_before(&mut self, node: Box<BushNode<T>>) {
+        let node = Box::into_raw(node);
+        unsafe {
+            let left = left_of(self.layer, self.current);
+            link_run(self.layer, left, self.current, node, node);
+        }
+    }
+
+
+    /// Insert the given node and its branches to the right of the cursor.
+    /// If the cursor is on the ghost position, the node becomes the first of the layer.
+    pub fn insert_node_after(&mut self, node: Box<BushNode<T>>) {
+        let node = Box::into_raw(node);
+        unsafe {
+            let right = right_of(self.layer, self.current);
+            link_run(self.layer, self.current, right, node, node);
+        }
+    }
+
+
+    /// Remove the current node and its branches from the bush, moving the cursor to the node to the right.
+    /// Return None, without removing anything, if the cursor is on the ghost position.
+    pub fn remove_current(&mut self) -> Option<Box<BushNode<T>>> {
+        if self.current.is_null() {
+            return None;
+        }
+
+        let node = self.current;
+        unsafe {
+            self.current = (*node).right;
+            unlink_run(node, node);
+            Some(Box::from_raw(node))
+        }
+    }
+
+
+    /// Split the current layer before the cursor, returning the nodes to its left as a new bush.
+    /// If the cursor is on the ghost position, the whole layer is moved.
+    pub fn split_before(&mut self) -> Bush<T> {
+        let end = unsafe { left_of(self.layer, self.current) };
+        if end.is_null() {
+            return Bush::new();
+        }
+
+        unsafe {
+            let start = (*self.layer).first;
+            unlink_run(start, end);
+            Bush::from_run(start, end)
+        }
+    }
+
+
+    /// Split the current layer after the cursor, returning the nodes to its right as a new bush.
+    /// If the cursor is on the ghost position, the whole layer is moved.
+    pub fn split_after(&mut self) -> Bush<T> {
+        let start = unsafe { right_of(self.layer, self.current) };
+        if start.is_null() {
+            return Bush::new();
+        }
+
+        unsafe {
+            let end = (*self.layer).last;
+            unlink_run(start, end);
+            Bush::from_run(start, end)
+        }
+    }
+
+
+    /// Move the top layer of the given bush to the left of the cursor.
+    /// If the cursor is on the ghost position, the nodes are moved to the end of the layer.
+    pub fn splice_before(&mut self, mut other: Bush<T>) {
+        if let Some((start, end)) = other.take_run() {
+            unsafe {
+                let left = left_of(self.layer, self.current);
+                link_run(self.layer, left, self.current, start, end);
+            }
+        }
+    }
+
+
+    /// Move the top layer of the given bush to the right of the cursor.
+    /// If the cursor is on the ghost position, the nodes are moved to the front of the layer.
+    pub fn splice_after(&mut self, mut other: Bush<T>) {
+        if let Some((start, end)) = other.take_run() {
+            unsafe {
+                let right = right_of(self.layer, self.current);
+                link_run(self.layer, self.current, right, start, end);
+            }
+        }
+    }
+
+
+    /// Return the number of layers between the cursor and the top layer of the bush it edits
+    pub fn depth(&self) -> usize {
+        self.as_cursor().depth()
+    }
+
+
+    /// Get the bush the cursor is editing
+    pub fn bush(&self) -> &Bush<T> {
+        self.bush
+    }
+
+}
+
+
+/// Get the node to the left of `node` in `layer`, treating a null `node` as the ghost position
+unsafe fn left_of<T>(layer: *const Layer<T>, node: *const BushNode<T>) -> *mut BushNode<T> {
+    if node.is_null() {
+        (*layer).last
+    } else {
+        (*node).left
+    }
+}
+
+
+/// Get the node to the right of `node` in `layer`, treating a null `node` as the ghost position
+unsafe fn right_of<T>(layer: *const Layer<T>, node: *const BushNode<T>) -> *mut BushNode<T> {
+    if node.is_null() {
+        (*layer).first
+    } else {
+        (*node).right
+    }
+}
+
+
+impl<T> Bush<T> {
+
+    /// Get a read-only cursor pointing to the first node of the bush's top layer
+    pub fn cursor(&self) -> Cursor<'_, T> {
+        Cursor {
+            layer: self.layer.as_ptr(),
+            current: self.layer().first,
+            bush: self,
+        }
+    }
+
+
+    /// Get a read-only cursor pointing to the node the given handle refers to, at any depth
+    ///
+    /// # Safety
+    ///
+    /// Same as `node`.
+    pub unsafe fn cursor_at(&self, handle: NodeHandle<T>) -> Cursor<'_, T> {
+        let node = self.node(handle);
+        Cursor {
+            layer: node.layer(),
+            current: handle.as_ptr(),
+            bush: self,
+        }
+    }
+
+
+    /// Get a cursor pointing to the first node of the bush's top layer
+    pub fn cursor_mut(&mut self) -> CursorMut<'_, T> {
+        CursorMut {
+            layer: self.layer.as_ptr(),
+            current: self.layer().first,
+            bush: self,
+        }
+    }
+
+
+    /// Get a cursor pointing to the node the given handle refers to, at any depth
+    ///
+    /// # Safety
+    ///
+    /// Same as `node_mut`.
+    pub unsafe fn cursor_mut_at(&mut self, handle: NodeHandle<T>) -> CursorMut<'_, T> {
+        let layer = self.node(handle).layer();
+        CursorMut {
+            layer,
+            current: handle.as_ptr() as *mut BushNode<T>,
+            bush: self,
+        }
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{self, items};
+
+    const NESTED: &str = "0 [1, 2 [3]], 4";
+
+
+
+    #[test]
+    fn walk_all_directions() {
+        let bush = test_util::bush(NESTED);
+        let mut cursor = bush.cursor();
+
+        assert_eq!(cursor.current(), Some(&0));
+        assert!(cursor.move_into_children());
+        assert_eq!(cursor.current(), Some(&1));
+        assert_eq!(cursor.depth(), 1);
+
+        cursor.move_right();
+        assert_eq!(cursor.current(), Some(&2));
+        assert!(cursor.move_into_children());
+        assert_eq!(cursor.current(), Some(&3));
+        assert!(!cursor.move_into_children());
+        assert_eq!(cursor.depth(), 2);
+
+        assert!(cursor.move_to_parent());
+        assert_eq!(cursor.current(), Some(&2));
+        assert!(cursor.move_to_parent());
+        assert_eq!(cursor.current(), Some(&0));
+        assert!(!cursor.move_to_parent());
+
+        cursor.move_right();
+        assert_eq!(cursor.current(), Some(&4));
+        assert_eq!(cursor.peek_left(), Some(&0));
+        assert_eq!(cursor.peek_right(), None);
+    }
+
+
+    #[test]
+    fn cursor_stays_in_nested_bush() {
+        let bush = test_util::bush(NESTED);
+        let children = bush.first_node().unwrap().children().unwrap();
+
+        let mut cursor = children.cursor();
+        assert_eq!(cursor.depth(), 0);
+        cursor.move_right();
+        assert!(cursor.move_into_children());
+        assert_eq!(cursor.depth(), 1);
+        assert!(cursor.move_to_parent());
+        assert!(!cursor.move_to_parent());
+        assert_eq!(cursor.current(), Some(&2));
+    }
+
+
+    #[test]
+    fn ghost_wraps_around() {
+        let bush = test_util::bush(NESTED);
+        let mut cursor = bush.cursor();
+
+        cursor.move_left();
+        assert_eq!(cursor.current(), None);
+        assert!(!cursor.move_into_children());
+        assert_eq!(cursor.peek_left(), Some(&4));
+        assert_eq!(cursor.peek_right(), Some(&0));
+
+        cursor.move_left();
+        assert_eq!(cursor.current(), Some(&4));
+        cursor.move_right();
+        cursor.move_right();
+        assert_eq!(cursor.current(), Some(&0));
+    }
+
+
+    #[test]
+    fn insert_around_cursor() {
+        let mut bush = test_util::bush(NESTED);
+        let mut cursor = bush.cursor_mut();
+
+        cursor.insert_before(-1);
+        cursor.insert_after(10);
+        assert_eq!(cursor.current(), Some(&mut 0));
+
+        // Inserting from the ghost position targets the ends of the layer
+        cursor.move_left();
+        cursor.move_left();
+        assert_eq!(cursor.current(), None);
+        cursor.insert_before(5);
+        cursor.insert_after(-2);
+
+        assert_eq!(items(&bush), vec![-2, -1, 0, 10, 4, 5]);
+        assert_eq!(bush.first_item(), Some(&-2));
+        assert_eq!(bush.last_item(), Some(&5));
+    }
+
+
+    #[test]
+    fn insert_into_nested_layer() {
+        let mut bush = test_util::bush(NESTED);
+        let mut cursor = bush.cursor_mut();
+
+        assert!(cursor.move_into_children());
+        cursor.move_right();
+        cursor.insert_after(20);
+        assert!(cursor.move_to_parent());
+        assert_eq!(cursor.current(), Some(&mut 0));
+
+        let children = bush.first_node().unwrap().children().unwrap();
+        assert_eq!(items(children), vec![1, 2, 20]);
+        assert_eq!(children.last_node().unwrap().parent_item(), Some(&0));
+    }
+
+
+    #[test]
+    fn remove_current() {
+        let mut bush = test_util::bush(NESTED);
+        let mut cursor = bush.cursor_mut();
+
+        let removed = cursor.remove_current().unwrap();
+        assert_eq!(removed.item, 0);
+        assert_eq!(removed.children().unwrap().top_layer_length(), 2);
+        assert_eq!(cursor.current(), Some(&mut 4));
+
+        assert_eq!(cursor.remove_current().map(|node| node.item), Some(4));
+        assert_eq!(cursor.current(), None);
+        assert!(cursor.remove_current().is_none());
+        assert!(bush.is_empty());
+    }
+
+
+    #[test]
+    fn remove_in_nested_layer() {
+        let mut bush = test_util::bush(NESTED);
+        let mut cursor = bush.cursor_mut();
+
+        assert!(cursor.move_into_children());
+        let removed = cursor.remove_current().unwrap();
+        assert_eq!(removed.item, 1);
+        assert!(removed.parent_node().is_none());
+        assert_eq!(cursor.current(), Some(&mut 2));
+
+        let children = bush.first_node().unwrap().children().unwrap();
+        assert_eq!(children.first_item(), Some(&2));
+        assert_eq!(children.last_item(), Some(&2));
+    }
+
+
+    #[test]
+    fn split() {
+        let mut bush = Bush::new();
+        for i in 0..6 {
+            bush.append(i);
+        }
+
+        let mut cursor = bush.cursor_mut();
+        cursor.move_right();
+        cursor.move_right();
+
+        let before = cursor.split_before();
+        let after = cursor.split_after();
+        assert_eq!(cursor.current(), Some(&mut 2));
+
+        assert_eq!(items(&before), vec![0, 1]);
+        assert_eq!(items(&after), vec![3, 4, 5]);
+        assert_eq!(items(&bush), vec![2]);
+        assert_eq!(after.last_item(), Some(&5));
+    }
+
+
+    #[test]
+    fn split_from_ghost_moves_everything() {
+        let mut bush = Bush::new();
+        for i in 0..3 {
+            bush.append(i);
+        }
+
+        let mut cursor = bush.cursor_mut();
+        cursor.move_left();
+        let all = cursor.split_after();
+
+        assert_eq!(items(&all), vec![0, 1, 2]);
+        assert!(bush.is_empty());
+    }
+
+
+    #[test]
+    fn splice() {
+        let mut bush = test_util::bush(NESTED);
+
+        let mut other = Bush::new();
+        other.append(7);
+        other.append(8);
+
+        let mut cursor = bush.cursor_mut();
+        assert!(cursor.move_into_children());
+        cursor.splice_after(other);
+        cursor.splice_before(Bush::new());
+
+        let mut front = Bush::new();
+        front.append(6);
+        cursor.splice_before(front);
+
+        let children = bush.first_node().unwrap().children().unwrap();
+        assert_eq!(items(children), vec![6, 1, 7, 8, 2]);
+        assert_eq!(children.first_item(), Some(&6));
+        for node in children.iter_nodes() {
+            assert_eq!(node.parent_item(), Some(&0));
+        }
+    }
+
+
+    #[test]
+    fn empty_children_layer() {
+        let mut bush = Bush::new();
+        bush.append(0);
+        bush.first_node_mut().unwrap().set_children(Some(Bush::new()));
+
+        let mut cursor = bush.cursor_mut();
+        assert!(cursor.move_into_children());
+        assert_eq!(cursor.current(), None);
+        cursor.insert_after(1);
+        cursor.insert_after(2);
+        assert!(cursor.move_to_parent());
+
+        let children = bush.first_node().unwrap().children().unwrap();
+        assert_eq!(items(children), vec![2, 1]);
+    }
+
+}
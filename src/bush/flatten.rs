@@ -0,0 +1,186 @@
+use super::{Bush, BushNode, DFSIter, IterItems, Layer};
+
+
+/// Iterator over the items of a bush or a node in the order `Bush::flatten` would leave them
+pub struct FlatIter<'a, T> {
+
+    /// The item of the node being flattened, which comes before its descendants; None for a bush
+    head: Option<&'a T>,
+    items: IterItems<T, DFSIter<'a, T>>
+
+}
+
+
+impl<'a, T> Iterator for FlatIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.head.take().or_else(|| self.items.next())
+    }
+}
+
+
+/// Splice the children of each node from `node` to the end of its layer right after it,
+/// and keep walking into them, so that deeper layers are flattened without recursing
+unsafe fn flatten_from<T>(mut node: *mut BushNode<T>) {
+    while !node.is_null() {
+        if let Some(mut children) = (*node).take_children() {
+            if let Some((start, end)) = children.take_run() {
+                (*node).insert_run_right(start, end);
+            }
+        }
+        node = (*node).right;
+    }
+}
+
+
+impl<T> Bush<T> {
+
+    /// Recursively flatten the bush into the top layer
+    pub fn flatten(&mut self) {
+        unsafe {
+            flatten_from(self.layer().first);
+        }
+    }
+
+
+    /// Flatten the layers deeper than `depth` into their ancestor layer at `depth`.
+    /// The top layer has depth 0, so `flatten_to_depth(0)` is the same as `flatten`.
+    pub fn flatten_to_depth(&mut self, depth: usize) {
+        let mut layers: Vec<*mut Layer<T>> = vec![self.layer.as_ptr()];
+
+        for _ in 0..depth {
+            let mut children_layers = Vec::new();
+            for layer in layers {
+                let mut node = unsafe { (*layer).first };
+                while !node.is_null() {
+                    unsafe {
+                        if let Some(children) = &(*node).children {
+                            children_layers.push(children.layer.as_ptr());
+                        }
+                        node = (*node).right;
+                    }
+                }
+            }
+            layers = children_layers;
+        }
+
+        for layer in layers {
+            unsafe {
+                flatten_from((*layer).first);
+            }
+        }
+    }
+
+
+    /// Get an iterator over the items of the bush in the order `flatten` would leave them in the top layer,
+    /// without modifying the bush
+    pub fn flat_iter(&self) -> FlatIter<'_, T> {
+        FlatIter { head: None, items: self.dfs_items() }
+    }
+
+}
+
+
+impl<T> BushNode<T> {
+
+    /// Recursively flatten the branches of the node into its own layer, right after it.
+    /// Panics if the node is detached, since its box only owns the node itself.
+    pub fn flatten_children_in_place(&mut self) {
+        assert!(!self.layer().is_null(), "cannot flatten into the layer of a detached node");
+        if let Some(mut children) = self.take_children() {
+            // Flatten the detached children first, walking them after the splice would go through this node
+            children.flatten();
+            if let Some((start, end)) = children.take_run() {
+                unsafe {
+                    self.insert_run_right(start, end);
+                }
+            }
+        }
+    }
+
+
+    /// Get an iterator over the item of the node followed by the items of its descendants,
+    /// in the order `flatten_children_in_place` would leave them, without modifying the node
+    pub fn flat_iter(&self) -> FlatIter<'_, T> {
+        FlatIter { head: Some(&self.item), items: self.dfs_items() }
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::BushPath;
+    use crate::test_util::{self, items};
+
+    const NESTED: &str = "0 [1 [2 [3], 4], 5], 6, 7 [8]";
+
+
+    #[test]
+    fn flat_iter() {
+        let bush: Bush<i32> = test_util::bush(NESTED);
+        let flat_items: Vec<i32> = bush.flat_iter().copied().collect();
+        assert_eq!(flat_items, (0..9).collect::<Vec<_>>());
+
+        // The bush is left untouched
+        assert_eq!(items(&bush), vec![0, 6, 7]);
+
+        let mut flat = bush.clone();
+        flat.flatten();
+        assert_eq!(items(&flat), flat_items);
+
+        assert_eq!(bush[0].flat_iter().copied().collect::<Vec<_>>(), vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(bush[1].flat_iter().copied().collect::<Vec<_>>(), vec![6]);
+        assert_eq!(Bush::<i32>::new().flat_iter().count(), 0);
+    }
+
+
+    #[test]
+    fn flatten_to_depth() {
+        let mut bush = test_util::bush(NESTED);
+        bush.flatten_to_depth(1);
+
+        assert_eq!(items(&bush), vec![0, 6, 7]);
+        assert_eq!(bush[0].children().map(items), Some(vec![1, 2, 3, 4, 5]));
+        assert_eq!(bush[2].children().map(items), Some(vec![8]));
+        assert!(bush[0].children().unwrap().iter_nodes().all(|node| node.children().is_none()));
+        assert_eq!(bush.total_node_count(), 9);
+        assert!(bush[0].children().unwrap()[3].parent_node().is_some_and(|parent| parent.item == 0));
+
+        // Depths past the bottom of the bush leave it unchanged
+        let mut bush: Bush<i32> = test_util::bush(NESTED);
+        bush.flatten_to_depth(5);
+        assert_eq!(bush, test_util::bush(NESTED));
+
+        bush.flatten_to_depth(0);
+        assert_eq!(items(&bush), (0..9).collect::<Vec<_>>());
+        assert_eq!(bush.total_node_count(), 9);
+    }
+
+
+    #[test]
+    fn flatten_children_in_place() {
+        let mut bush = test_util::bush(NESTED);
+
+        bush[&BushPath::from([0, 0])].flatten_children_in_place();
+        assert_eq!(bush[0].children().map(items), Some(vec![1, 2, 3, 4, 5]));
+        assert_eq!(items(&bush), vec![0, 6, 7]);
+
+        bush[2].flatten_children_in_place();
+        assert_eq!(items(&bush), vec![0, 6, 7, 8]);
+        assert_eq!(bush.last_item(), Some(&8));
+        assert!(bush[2].children().is_none());
+
+        bush[0].flatten_children_in_place();
+        assert_eq!(items(&bush), (0..9).collect::<Vec<_>>());
+        assert_eq!(bush.len(), 9);
+        assert_eq!(bush.total_node_count(), 9);
+
+        // A node without children is left as is
+        bush[4].flatten_children_in_place();
+        assert_eq!(bush.len(), 9);
+    }
+
+}
@@ -0,0 +1,334 @@
+use std::fmt;
+use std::error::Error;
+
+use super::{node_layer, Bush, BushNode, BushSlice, EmptyChildren, NodeHandle};
+
+
+/// A node handle bound to the bush it was created from.
+///
+/// The handle records the identity of the bush and the bush's generation, which changes whenever
+/// nodes are removed from the bush at any depth. Checked accesses compare both before touching the
+/// node, so a handle used on another bush, or after a removal, is rejected instead of dereferenced.
+pub struct CheckedHandle<T> {
+
+    node: NodeHandle<T>,
+    bush_id: u64,
+    generation: u64,
+
+}
+
+
+impl<T> CheckedHandle<T> {
+
+    /// Get the unchecked handle, for use on the fast path
+    pub fn unchecked(&self) -> NodeHandle<T> {
+        self.node
+    }
+
+}
+
+
+impl<T> Clone for CheckedHandle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+
+impl<T> Copy for CheckedHandle<T> {}
+
+
+impl<T> PartialEq for CheckedHandle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.node == other.node && self.bush_id == other.bush_id && self.generation == other.generation
+    }
+}
+
+
+impl<T> Eq for CheckedHandle<T> {}
+
+
+impl<T> fmt::Debug for CheckedHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CheckedHandle")
+            .field("node", &self.node.as_ptr())
+            .field("bush_id", &self.bush_id)
+            .field("generation", &self.generation)
+            .finish()
+    }
+}
+
+
+/// Reason why a checked handle was rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HandleError {
+
+    /// The handle was created by another bush
+    ForeignBush,
+
+    /// Nodes were removed from the bush since the handle was created, so its node may no longer exist
+    StaleHandle,
+
+    /// The node is in a nested layer, but the operation requires a node of the bush's top layer
+    NotInTopLayer,
+
+    /// The end node of a slice does not follow its start node in the layer
+    InvalidSlice,
+
+}
+
+
+impl fmt::Display for HandleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            HandleError::ForeignBush => "the handle belongs to another bush",
+            HandleError::StaleHandle => "nodes were removed from the bush after the handle was created",
+            HandleError::NotInTopLayer => "the node is not in the bush's top layer",
+            HandleError::InvalidSlice => "the end node does not follow the start node in the layer",
+        })
+    }
+}
+
+
+impl Error for HandleError {}
+
+
+impl<T> Bush<T> {
+
+    /// Create a checked handle to the given node, which must be in this bush at any depth
+    pub fn checked_handle(&self, node: &BushNode<T>) -> Result<CheckedHandle<T>, HandleError> {
+        let node = node.into_handle();
+        if unsafe { self.owns_node(node.as_ptr()) } {
+            Ok(self.make_checked(node))
+        } else {
+            Err(HandleError::ForeignBush)
+        }
+    }
+
+
+    /// Get a checked handle to the first node of the bush's top layer
+    pub fn first_checked_handle(&self) -> Option<CheckedHandle<T>> {
+        self.first_node_handle().map(|node| self.make_checked(node))
+    }
+
+
+    /// Get a checked handle to the last node of the bush's top layer
+    pub fn last_checked_handle(&self) -> Option<CheckedHandle<T>> {
+        self.last_node_handle().map(|node| self.make_checked(node))
+    }
+
+
+    /// Get the node the given handle refers to, at any depth
+    pub fn try_get(&self, handle: &CheckedHandle<T>) -> Result<&BushNode<T>, HandleError> {
+        self.validate(handle)?;
+        Ok(unsafe { &*handle.node.as_ptr() })
+    }
+
+
+    /// Get the node the given handle refers to, at any depth
+    pub fn try_get_mut(&mut self, handle: &CheckedHandle<T>) -> Result<&mut BushNode<T>, HandleError> {
+        self.validate(handle)?;
+        Ok(unsafe { &mut *(handle.node.as_ptr() as *mut BushNode<T>) })
+    }
+
+
+    /// Remove the given node and its branches from any layer of the bush.
+    /// If the parent node's children are left empty, `empty` decides whether it keeps them.
+    pub fn try_remove_node(&mut self, handle: &CheckedHandle<T>, empty: EmptyChildren) -> Result<Box<BushNode<T>>, HandleError> {
+        self.validate(handle)?;
+        Ok(unsafe { self.remove_node(handle.node, empty) })
+    }
+
+
+    /// Extract the given node and its branches from the bush's top layer
+    pub fn try_extract_node(&mut self, handle: &CheckedHandle<T>) -> Result<Box<BushNode<T>>, HandleError> {
+        self.validate_top_layer(handle)?;
+        Ok(unsafe { self.extract_node(handle.node) })
+    }
+
+
+    /// Extract the nodes from `start_node` to `end_node` and their branches from the bush's top layer
+    pub fn try_extract_slice(&mut self, start_node: &CheckedHandle<T>, end_node: &CheckedHandle<T>) -> Result<BushSlice<T>, HandleError> {
+        self.validate_top_layer(start_node)?;
+        self.validate_top_layer(end_node)?;
+
+        let start = unsafe { &*start_node.node.as_ptr() };
+        if !start.iter_nodes_right().any(|node| node.into_handle() == end_node.node) {
+            return Err(HandleError::InvalidSlice);
+        }
+
+        Ok(unsafe { self.extract_slice(start_node.node, end_node.node) })
+    }
+
+
+    fn make_checked(&self, node: NodeHandle<T>) -> CheckedHandle<T> {
+        CheckedHandle {
+            node,
+            bush_id: self.layer().id,
+            generation: self.layer().generation,
+        }
+    }
+
+
+    /// Check that the handle's node still exists in this bush.
+    /// The node is only dereferenced after both checks pass.
+    fn validate(&self, handle: &CheckedHandle<T>) -> Result<(), HandleError> {
+        if handle.bush_id != self.layer().id {
+            Err(HandleError::ForeignBush)
+        } else if handle.generation != self.layer().generation {
+            Err(HandleError::StaleHandle)
+        } else {
+            Ok(())
+        }
+    }
+
+
+    fn validate_top_layer(&self, handle: &CheckedHandle<T>) -> Result<(), HandleError> {
+        self.validate(handle)?;
+        if unsafe { node_layer(handle.node.as_ptr()) } == self.layer.as_ptr() {
+            Ok(())
+        } else {
+            Err(HandleError::NotInTopLayer)
+        }
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::super::BushPath;
+    use super::*;
+    use crate::test_util;
+
+    const NESTED: &str = "0 [10, 11], 1, 2";
+
+
+    #[test]
+    fn get_and_extract() {
+        let mut bush = test_util::bush(NESTED);
+        let last = bush.last_checked_handle().unwrap();
+
+        assert_eq!(bush.try_get(&last).unwrap().item, 2);
+        bush.try_get_mut(&last).unwrap().item = 20;
+        assert_eq!(bush.last_item(), Some(&20));
+
+        let node = bush.try_extract_node(&last).unwrap();
+        assert_eq!(node.item, 20);
+        assert_eq!(bush.last_item(), Some(&1));
+    }
+
+
+    #[test]
+    fn stale_after_removal() {
+        let mut bush: Bush<i32> = test_util::bush(NESTED);
+        let first = bush.first_checked_handle().unwrap();
+        let last = bush.last_checked_handle().unwrap();
+
+        bush.try_extract_node(&last).unwrap();
+
+        assert_eq!(bush.try_get(&last).err(), Some(HandleError::StaleHandle));
+        assert_eq!(bush.try_extract_node(&last).err(), Some(HandleError::StaleHandle));
+        // Any removal invalidates every handle of the bush, not only the removed node's
+        assert_eq!(bush.try_get(&first).err(), Some(HandleError::StaleHandle));
+        assert!(bush.try_get(&bush.first_checked_handle().unwrap()).is_ok());
+    }
+
+
+    #[test]
+    fn stale_after_nested_removal() {
+        let mut bush: Bush<i32> = test_util::bush(NESTED);
+        let first = bush.first_checked_handle().unwrap();
+
+        bush.remove_at_path(&BushPath::from([0, 0]));
+
+        assert_eq!(bush.try_get(&first).err(), Some(HandleError::StaleHandle));
+
+        let first = bush.first_checked_handle().unwrap();
+        bush.first_node_mut().unwrap().take_children();
+        assert_eq!(bush.try_get(&first).err(), Some(HandleError::StaleHandle));
+    }
+
+
+    #[test]
+    fn stale_after_children_swap() {
+        let mut bush: Bush<i32> = test_util::bush(NESTED);
+        let child = bush.checked_handle(&bush[&BushPath::from([0, 1])]).unwrap();
+
+        // Replacing a children layer takes its nodes out of the bush, like removing them
+        let old = bush[0].set_children(Some(test_util::bush("20, 21")));
+        assert_eq!(old.map(|children| children.len()), Some(2));
+        assert_eq!(bush.try_get(&child).err(), Some(HandleError::StaleHandle));
+
+        // Moving the layer to another node of the same bush is a removal too
+        let moved = bush.checked_handle(&bush[&BushPath::from([0, 0])]).unwrap();
+        let children = bush[0].take_children();
+        bush[2].set_children(children);
+        assert_eq!(bush.try_get(&moved).err(), Some(HandleError::StaleHandle));
+        assert_eq!(bush[&BushPath::from([2, 0])].item, 20);
+
+        // Giving children to a nested node that had none removes nothing, taking them back does
+        let top = bush.first_checked_handle().unwrap();
+        bush[&BushPath::from([2, 0])].set_children(Some(test_util::bush("200")));
+        assert!(bush.try_get(&top).is_ok());
+        bush[&BushPath::from([2, 0])].set_children(None);
+        assert_eq!(bush.try_get(&top).err(), Some(HandleError::StaleHandle));
+    }
+
+
+    #[test]
+    fn valid_after_insertion() {
+        let mut bush = test_util::bush(NESTED);
+        let last = bush.last_checked_handle().unwrap();
+
+        bush.append(3);
+        bush.prepend(-1);
+
+        assert_eq!(bush.try_get(&last).unwrap().item, 2);
+    }
+
+
+    #[test]
+    fn foreign_bush() {
+        let mut bush: Bush<i32> = test_util::bush(NESTED);
+        let mut other = test_util::bush(NESTED);
+        let handle = other.first_checked_handle().unwrap();
+
+        assert_eq!(bush.try_get(&handle).err(), Some(HandleError::ForeignBush));
+        assert_eq!(bush.try_extract_node(&handle).err(), Some(HandleError::ForeignBush));
+        assert!(other.try_extract_node(&handle).is_ok());
+
+        let node = other.first_node().unwrap();
+        assert_eq!(bush.checked_handle(node).err(), Some(HandleError::ForeignBush));
+    }
+
+
+    #[test]
+    fn nested_node() {
+        let mut bush = test_util::bush(NESTED);
+        let child = bush.first_node().unwrap().children().unwrap().first_node().unwrap();
+        let handle = bush.checked_handle(child).unwrap();
+
+        assert_eq!(bush.try_get(&handle).unwrap().item, 10);
+        assert_eq!(bush.try_extract_node(&handle).err(), Some(HandleError::NotInTopLayer));
+        assert_eq!(bush.try_extract_slice(&handle, &handle).err(), Some(HandleError::NotInTopLayer));
+
+        assert_eq!(bush.try_remove_node(&handle, EmptyChildren::Keep).map(|node| node.item), Ok(10));
+        assert_eq!(bush.try_remove_node(&handle, EmptyChildren::Keep).err(), Some(HandleError::StaleHandle));
+    }
+
+
+    #[test]
+    fn extract_slice() {
+        let mut bush: Bush<i32> = test_util::bush(NESTED);
+        let first = bush.first_checked_handle().unwrap();
+        let last = bush.last_checked_handle().unwrap();
+
+        assert_eq!(bush.try_extract_slice(&last, &first).err(), Some(HandleError::InvalidSlice));
+
+        let slice = bush.try_extract_slice(&first, &last).unwrap();
+        assert!(bush.is_empty());
+        assert_eq!(Bush::from_slice(slice).total_node_count(), 5);
+    }
+
+}
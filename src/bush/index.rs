@@ -0,0 +1,358 @@
+use std::ops::{Bound, Index, IndexMut, RangeBounds};
+use std::ptr;
+
+use super::{link_run, null_node, unlink_run, Bush, BushNode, Layer};
+
+
+impl<T> Bush<T> {
+
+    /// Return the number of nodes in the bush's top layer, same as `top_layer_length`
+    pub fn len(&self) -> usize {
+        self.layer().len
+    }
+
+
+    /// Get the node at index `i` of the bush's top layer
+    pub fn get(&self, i: usize) -> Option<&BushNode<T>> {
+        let node = self.node_ptr(i);
+        if node.is_null() {
+            None
+        } else {
+            unsafe { Some(&*node) }
+        }
+    }
+
+
+    /// Get the node at index `i` of the bush's top layer
+    pub fn get_mut(&mut self, i: usize) -> Option<&mut BushNode<T>> {
+        let node = self.node_ptr(i);
+        if node.is_null() {
+            None
+        } else {
+            unsafe { Some(&mut *node) }
+        }
+    }
+
+
+    /// Insert a new node at index `i` of the bush's top layer, shifting the following nodes to the right.
+    /// Panics if `i` is greater than the layer's length.
+    pub fn insert(&mut self, i: usize, item: T) {
+        self.insert_node(i, BushNode::new(item));
+    }
+
+
+    /// Insert the given node and its branches at index `i` of the bush's top layer.
+    /// Panics if `i` is greater than the layer's length.
+    pub fn insert_node(&mut self, i: usize, node: Box<BushNode<T>>) {
+        let len = self.len();
+        assert!(i <= len, "insertion index (is {i}) should be <= len (is {len})");
+
+        // Inserting at the end links the node before the null right end of the layer
+        let right = self.node_ptr(i);
+        let node = Box::into_raw(node);
+        unsafe {
+            let left = if right.is_null() { self.layer().last } else { (*right).left };
+            link_run(self.layer.as_ptr(), left, right, node, node);
+        }
+    }
+
+
+    /// Remove the node at index `i` of the bush's top layer and return it with its branches.
+    /// Panics if `i` is out of bounds.
+    pub fn remove(&mut self, i: usize) -> Box<BushNode<T>> {
+        let len = self.len();
+        assert!(i < len, "removal index (is {i}) should be < len (is {len})");
+
+        let node = self.node_ptr(i);
+        unsafe {
+            unlink_run(node, node);
+            Box::from_raw(node)
+        }
+    }
+
+
+    /// Swap the nodes at indices `i` and `j` of the bush's top layer, along with their branches.
+    /// Panics if either index is out of bounds.
+    pub fn swap(&mut self, i: usize, j: usize) {
+        let len = self.len();
+        assert!(i < len && j < len, "swap indices (are {i} and {j}) should be < len (is {len})");
+
+        let (i, j) = if i <= j { (i, j) } else { (j, i) };
+        let a = self.node_ptr(i);
+        let b = self.node_ptr(j);
+        unsafe {
+            swap_in_layer(self.layer.as_ptr(), a, b);
+        }
+    }
+
+
+    /// Remove the nodes from index `len` onwards from the bush's top layer, dropping them and their branches.
+    /// Does nothing if the layer is not longer than `len`.
+    pub fn truncate(&mut self, len: usize) {
+        let start = self.node_ptr(len);
+        if start.is_null() {
+            return;
+        }
+
+        unsafe {
+            let end = self.layer().last;
+            unlink_run(start, end);
+            drop(Bush::from_run(start, end));
+        }
+    }
+
+
+    /// Remove the first node of the bush's top layer and return it with its branches
+    pub fn pop_front(&mut self) -> Option<Box<BushNode<T>>> {
+        let first = self.first_node_handle()?;
+        Some(unsafe { self.extract_node(first) })
+    }
+
+
+    /// Remove the last node of the bush's top layer and return it with its branches
+    pub fn pop_back(&mut self) -> Option<Box<BushNode<T>>> {
+        let last = self.last_node_handle()?;
+        Some(unsafe { self.extract_node(last) })
+    }
+
+
+    /// Get a pointer to the node at index `i` of the top layer, or null if out of bounds
+    pub(super) fn node_ptr(&self, i: usize) -> *mut BushNode<T> {
+        unsafe { layer_node(self.layer.as_ptr(), i) }
+    }
+
+
+    /// Resolve a range of indices of the top layer into its start and end, for the named operation.
+    /// Panics if the range is decreasing or goes past the end of the layer.
+    pub(super) fn layer_range<R>(&self, range: R, operation: &str) -> (usize, usize)
+    where
+        R: RangeBounds<usize>
+    {
+        let len = self.len();
+        let start = match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start + 1,
+            Bound::Unbounded => 0
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&end) => end + 1,
+            Bound::Excluded(&end) => end,
+            Bound::Unbounded => len
+        };
+        assert!(start <= end, "{operation} range starts at {start} but ends at {end}");
+        assert!(end <= len, "{operation} range end (is {end}) should be <= len (is {len})");
+
+        (start, end)
+    }
+
+}
+
+
+/// Get a pointer to the node at index `i` of `layer`, or null if out of bounds.
+/// The layer is walked from the end nearest to the index.
+pub(super) unsafe fn layer_node<T>(layer: *const Layer<T>, i: usize) -> *mut BushNode<T> {
+    let len = (*layer).len;
+    if i >= len {
+        return null_node();
+    }
+
+    if i < len / 2 {
+        let mut node = (*layer).first;
+        for _ in 0..i {
+            node = (*node).right;
+        }
+        node
+    } else {
+        let mut node = (*layer).last;
+        for _ in i + 1..len {
+            node = (*node).left;
+        }
+        node
+    }
+}
+
+
+/// Link `left` and `right` next to each other in `layer`, a null end standing for the end of the layer
+unsafe fn join<T>(layer: *mut Layer<T>, left: *mut BushNode<T>, right: *mut BushNode<T>) {
+    if left.is_null() {
+        (*layer).first = right;
+    } else {
+        (*left).right = right;
+    }
+
+    if right.is_null() {
+        (*layer).last = left;
+    } else {
+        (*right).left = left;
+    }
+}
+
+
+/// Swap the positions of the nodes `a` and `b` in `layer`, where `a` comes before `b`.
+/// The nodes are relinked in place, so no node is removed and handles to them stay valid.
+unsafe fn swap_in_layer<T>(layer: *mut Layer<T>, a: *mut BushNode<T>, b: *mut BushNode<T>) {
+    if ptr::addr_eq(a, b) {
+        return;
+    }
+
+    let left = (*a).left;
+    let right = (*b).right;
+
+    if ptr::addr_eq((*a).right, b) {
+        join(layer, left, b);
+        join(layer, b, a);
+        join(layer, a, right);
+    } else {
+        let after_a = (*a).right;
+        let before_b = (*b).left;
+        join(layer, left, b);
+        join(layer, b, after_a);
+        join(layer, before_b, a);
+        join(layer, a, right);
+    }
+}
+
+
+impl<T> Index<usize> for Bush<T> {
+    type Output = BushNode<T>;
+
+    fn index(&self, i: usize) -> &Self::Output {
+        let len = self.len();
+        self.get(i).unwrap_or_else(|| panic!("index out of bounds: the len is {len} but the index is {i}"))
+    }
+}
+
+
+impl<T> IndexMut<usize> for Bush<T> {
+    fn index_mut(&mut self, i: usize) -> &mut Self::Output {
+        let len = self.len();
+        self.get_mut(i).unwrap_or_else(|| panic!("index out of bounds: the len is {len} but the index is {i}"))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{self, items};
+
+
+    #[test]
+    fn get_and_index() {
+        let mut bush = test_util::bush("0 1 2 3 4");
+
+        assert_eq!(bush.len(), 5);
+        assert_eq!(bush.get(0).map(|node| node.item), Some(0));
+        assert_eq!(bush.get(3).map(|node| node.item), Some(3));
+        assert!(bush.get(5).is_none());
+
+        bush.get_mut(1).unwrap().item = 10;
+        bush[4].item = 40;
+        assert_eq!(bush[1].item, 10);
+        assert_eq!(items(&bush), vec![0, 10, 2, 3, 40]);
+    }
+
+
+    #[test]
+    #[should_panic(expected = "index out of bounds")]
+    fn index_out_of_bounds() {
+        let bush: Bush<i32> = test_util::bush("0 1");
+        let _ = &bush[2];
+    }
+
+
+    #[test]
+    fn insert_and_remove() {
+        let mut bush = test_util::bush("1 3");
+
+        bush.insert(0, 0);
+        bush.insert(2, 2);
+        bush.insert(4, 4);
+        assert_eq!(items(&bush), vec![0, 1, 2, 3, 4]);
+        assert_eq!(bush.len(), 5);
+
+        assert_eq!(bush.remove(2).item, 2);
+        assert_eq!(bush.remove(3).item, 4);
+        assert_eq!(bush.remove(0).item, 0);
+        assert_eq!(items(&bush), vec![1, 3]);
+        assert_eq!(bush.len(), 2);
+        assert_eq!(bush.last_item(), Some(&3));
+    }
+
+
+    #[test]
+    #[should_panic(expected = "insertion index")]
+    fn insert_out_of_bounds() {
+        let mut bush = test_util::bush("0");
+        bush.insert(2, 1);
+    }
+
+
+    #[test]
+    fn remove_keeps_branches() {
+        let mut bush = test_util::bush("0 1");
+        bush[1].set_children(Some(test_util::bush("10 11")));
+
+        let node = bush.remove(1);
+        assert_eq!(node.children().map(items), Some(vec![10, 11]));
+        assert_eq!(bush.total_node_count(), 1);
+
+        bush.insert_node(0, node);
+        assert_eq!(bush.total_node_count(), 4);
+        assert_eq!(bush[0].children().unwrap()[1].parent_item(), Some(&1));
+    }
+
+
+    #[test]
+    fn swap() {
+        let mut bush = test_util::bush("0 1 2 3");
+
+        bush.swap(0, 3);
+        assert_eq!(items(&bush), vec![3, 1, 2, 0]);
+        bush.swap(2, 1);
+        assert_eq!(items(&bush), vec![3, 2, 1, 0]);
+        bush.swap(0, 1);
+        assert_eq!(items(&bush), vec![2, 3, 1, 0]);
+        bush.swap(2, 2);
+        assert_eq!(items(&bush), vec![2, 3, 1, 0]);
+
+        assert_eq!(bush.first_item(), Some(&2));
+        assert_eq!(bush.last_item(), Some(&0));
+        assert_eq!(bush.iter_nodes().last().unwrap().iter_items_left().copied().collect::<Vec<_>>(), vec![0, 1, 3, 2]);
+    }
+
+
+    #[test]
+    fn swap_keeps_handles() {
+        let mut bush = test_util::bush("0 1 2");
+        let handle = bush.first_checked_handle().unwrap();
+
+        bush.swap(0, 2);
+        assert_eq!(bush.try_get(&handle).unwrap().item, 0);
+        assert_eq!(bush.last_item(), Some(&0));
+    }
+
+
+    #[test]
+    fn truncate_and_pop() {
+        let mut bush = test_util::bush("0 1 2 3 4 5");
+        bush[4].set_children(Some(test_util::bush("10")));
+
+        bush.truncate(10);
+        assert_eq!(bush.len(), 6);
+        bush.truncate(4);
+        assert_eq!(items(&bush), vec![0, 1, 2, 3]);
+        assert_eq!(bush.total_node_count(), 4);
+
+        assert_eq!(bush.pop_front().map(|node| node.item), Some(0));
+        assert_eq!(bush.pop_back().map(|node| node.item), Some(3));
+        assert_eq!(items(&bush), vec![1, 2]);
+
+        bush.truncate(0);
+        assert!(bush.is_empty());
+        assert_eq!(bush.len(), 0);
+        assert!(bush.pop_front().is_none());
+        assert!(bush.pop_back().is_none());
+    }
+
+}
@@ -0,0 +1,400 @@
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+
+use super::{null_node, Bush, BushNode};
+
+
+// A mutable node can insert siblings and replace its children, so handing out mutable nodes through an
+// `Iterator` would let two of them alias, or let one drop the children another one is being walked in.
+// The node walks below lend out one node at a time instead, and read the links of the last node only
+// when asked for the next one, so they follow any edit made through it. The item iterators hand out
+// only items, which are disjoint from the links, and are regular iterators.
+
+
+/// Get the first child of `node`, or null if it has none.
+/// Only the `children` field is read, so a mutable borrow of the node's item may be alive.
+unsafe fn first_child<T>(node: *mut BushNode<T>) -> *mut BushNode<T> {
+    match &(*node).children {
+        Some(children) => (*children.layer.as_ptr()).first,
+        None => null_node()
+    }
+}
+
+
+/// Depth first walk over raw node pointers, reading the links lazily
+struct RawDFS<T> {
+
+    /// The nodes whose children are being walked
+    ancestors: Vec<*mut BushNode<T>>,
+
+    /// The last yielded node, or the first node to yield if nothing was yielded yet
+    current: *mut BushNode<T>,
+
+    started: bool,
+
+}
+
+
+impl<T> RawDFS<T> {
+
+    fn new(first: *mut BushNode<T>) -> Self {
+        Self {
+            ancestors: Vec::new(),
+            current: first,
+            started: false,
+        }
+    }
+
+
+    unsafe fn advance(&mut self) -> *mut BushNode<T> {
+        if !self.started {
+            self.started = true;
+            return self.current;
+        }
+        if self.current.is_null() {
+            return null_node();
+        }
+
+        let first = first_child(self.current);
+        if !first.is_null() {
+            self.ancestors.push(self.current);
+            self.current = first;
+            return first;
+        }
+
+        // Climb up until a node with a right sibling is found
+        let mut node = self.current;
+        loop {
+            let right = (*node).right;
+            if !right.is_null() {
+                self.current = right;
+                return right;
+            }
+            match self.ancestors.pop() {
+                Some(parent) => node = parent,
+                None => {
+                    self.current = null_node();
+                    return null_node();
+                }
+            }
+        }
+    }
+
+}
+
+
+/// Breadth first walk over raw node pointers, reading the links lazily
+struct RawBFS<T> {
+
+    /// The yielded nodes whose children are still to be walked
+    parents: VecDeque<*mut BushNode<T>>,
+
+    /// The last yielded node, or the first node to yield if nothing was yielded yet
+    current: *mut BushNode<T>,
+
+    started: bool,
+
+}
+
+
+impl<T> RawBFS<T> {
+
+    fn new(first: *mut BushNode<T>) -> Self {
+        Self {
+            parents: VecDeque::new(),
+            current: first,
+            started: false,
+        }
+    }
+
+
+    unsafe fn advance(&mut self) -> *mut BushNode<T> {
+        if !self.started {
+            self.started = true;
+            return self.current;
+        }
+        if self.current.is_null() {
+            return null_node();
+        }
+
+        self.parents.push_back(self.current);
+
+        let right = (*self.current).right;
+        if !right.is_null() {
+            self.current = right;
+            return right;
+        }
+
+        // The current layer is over, continue with the children of the oldest parent that has any
+        while let Some(parent) = self.parents.pop_front() {
+            let first = first_child(parent);
+            if !first.is_null() {
+                self.current = first;
+                return first;
+            }
+        }
+
+        self.current = null_node();
+        null_node()
+    }
+
+}
+
+
+/// Depth first walk lending out one mutable node at a time.
+/// Edits made through a node, like replacing its children or inserting siblings, are followed by the walk.
+pub struct DFSNodesMut<'a, T> {
+
+    raw: RawDFS<T>,
+    marker: PhantomData<&'a mut BushNode<T>>
+
+}
+
+
+impl<T> DFSNodesMut<'_, T> {
+
+    /// Get the next node of the walk
+    pub fn next_node(&mut self) -> Option<&mut BushNode<T>> {
+        let node = unsafe { self.raw.advance() };
+        if node.is_null() {
+            None
+        } else {
+            unsafe { Some(&mut *node) }
+        }
+    }
+
+}
+
+
+/// Breadth first walk lending out one mutable node at a time.
+/// Edits made through a node, like replacing its children or inserting siblings, are followed by the walk.
+pub struct BFSNodesMut<'a, T> {
+
+    raw: RawBFS<T>,
+    marker: PhantomData<&'a mut BushNode<T>>
+
+}
+
+
+impl<T> BFSNodesMut<'_, T> {
+
+    /// Get the next node of the walk
+    pub fn next_node(&mut self) -> Option<&mut BushNode<T>> {
+        let node = unsafe { self.raw.advance() };
+        if node.is_null() {
+            None
+        } else {
+            unsafe { Some(&mut *node) }
+        }
+    }
+
+}
+
+
+/// Depth first iterator over mutable items
+pub struct DFSItemsMut<'a, T> {
+
+    raw: RawDFS<T>,
+    marker: PhantomData<&'a mut T>
+
+}
+
+
+impl<'a, T> Iterator for DFSItemsMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = unsafe { self.raw.advance() };
+        if node.is_null() {
+            None
+        } else {
+            unsafe { Some(&mut (*node).item) }
+        }
+    }
+}
+
+
+/// Breadth first iterator over mutable items
+pub struct BFSItemsMut<'a, T> {
+
+    raw: RawBFS<T>,
+    marker: PhantomData<&'a mut T>
+
+}
+
+
+impl<'a, T> Iterator for BFSItemsMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = unsafe { self.raw.advance() };
+        if node.is_null() {
+            None
+        } else {
+            unsafe { Some(&mut (*node).item) }
+        }
+    }
+}
+
+
+impl<T> Bush<T> {
+
+    /// Walk the nodes of the bush mutably in depth first order
+    pub fn dfs_nodes_mut(&mut self) -> DFSNodesMut<'_, T> {
+        DFSNodesMut {
+            raw: RawDFS::new(self.layer().first),
+            marker: PhantomData
+        }
+    }
+
+
+    /// Walk the nodes of the bush mutably in breadth first order
+    pub fn bfs_nodes_mut(&mut self) -> BFSNodesMut<'_, T> {
+        BFSNodesMut {
+            raw: RawBFS::new(self.layer().first),
+            marker: PhantomData
+        }
+    }
+
+
+    /// Get a depth first iterator over the mutable items of the bush
+    pub fn dfs_items_mut(&mut self) -> DFSItemsMut<'_, T> {
+        DFSItemsMut {
+            raw: RawDFS::new(self.layer().first),
+            marker: PhantomData
+        }
+    }
+
+
+    /// Get a breadth first iterator over the mutable items of the bush
+    pub fn bfs_items_mut(&mut self) -> BFSItemsMut<'_, T> {
+        BFSItemsMut {
+            raw: RawBFS::new(self.layer().first),
+            marker: PhantomData
+        }
+    }
+
+}
+
+
+// Like `BushNode::dfs_nodes`, the item iterators cover the descendants of the node. There are no node walks here:
+// a lent descendant could read the node through `parent_node` while the node is still mutably borrowed.
+
+impl<T> BushNode<T> {
+
+    fn first_child_ptr(&mut self) -> *mut BushNode<T> {
+        match &self.children {
+            Some(children) => children.layer().first,
+            None => null_node()
+        }
+    }
+
+
+    /// Get a depth first iterator over the mutable items of the descendants of the node
+    pub fn dfs_items_mut(&mut self) -> DFSItemsMut<'_, T> {
+        DFSItemsMut {
+            raw: RawDFS::new(self.first_child_ptr()),
+            marker: PhantomData
+        }
+    }
+
+
+    /// Get a breadth first iterator over the mutable items of the descendants of the node
+    pub fn bfs_items_mut(&mut self) -> BFSItemsMut<'_, T> {
+        BFSItemsMut {
+            raw: RawBFS::new(self.first_child_ptr()),
+            marker: PhantomData
+        }
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+
+    const NESTED: &str = "0 [1 [2, 3], 4], 5, 6 [7]";
+
+
+    #[test]
+    fn items_mut() {
+        let mut bush = test_util::bush(NESTED);
+
+        let items: Vec<&mut i32> = bush.dfs_items_mut().collect();
+        assert_eq!(items.iter().map(|item| **item).collect::<Vec<_>>(), vec![0, 1, 2, 3, 4, 5, 6, 7]);
+        for item in items {
+            *item *= 10;
+        }
+
+        let items: Vec<&mut i32> = bush.bfs_items_mut().collect();
+        assert_eq!(items.iter().map(|item| **item).collect::<Vec<_>>(), vec![0, 50, 60, 10, 40, 70, 20, 30]);
+        for item in items {
+            *item += 1;
+        }
+
+        assert_eq!(bush.dfs_items().copied().collect::<Vec<_>>(), vec![1, 11, 21, 31, 41, 51, 61, 71]);
+        assert_eq!(Bush::<i32>::new().dfs_items_mut().count(), 0);
+    }
+
+
+    #[test]
+    fn node_items_mut() {
+        let mut bush: Bush<i32> = test_util::bush(NESTED);
+
+        for item in bush[0].dfs_items_mut() {
+            *item = -*item;
+        }
+        assert_eq!(bush[0].bfs_items_mut().map(|item| *item).collect::<Vec<_>>(), vec![-1, -4, -2, -3]);
+        assert_eq!(bush.dfs_items().copied().collect::<Vec<_>>(), vec![0, -1, -2, -3, -4, 5, 6, 7]);
+        assert_eq!(bush[1].dfs_items_mut().count(), 0);
+    }
+
+
+    #[test]
+    fn dfs_nodes_mut() {
+        let mut bush = test_util::bush(NESTED);
+
+        let mut visited = Vec::new();
+        let mut nodes = bush.dfs_nodes_mut();
+        while let Some(node) = nodes.next_node() {
+            visited.push(node.item);
+            // Replace the children of a node, which are walked next
+            if node.item == 5 {
+                node.set_children(Some([50, 51].into_iter().collect()));
+            }
+            // Drop the children of a node before they are walked
+            if node.item == 1 {
+                node.take_children();
+            }
+        }
+
+        assert_eq!(visited, vec![0, 1, 4, 5, 50, 51, 6, 7]);
+        assert_eq!(bush.total_node_count(), 8);
+    }
+
+
+    #[test]
+    fn bfs_nodes_mut() {
+        let mut bush = test_util::bush(NESTED);
+
+        let mut visited = Vec::new();
+        let mut nodes = bush.bfs_nodes_mut();
+        while let Some(node) = nodes.next_node() {
+            visited.push(node.item);
+            if node.item == 4 {
+                node.insert_right_node(BushNode::new(45));
+                node.set_children(Some([40].into_iter().collect()));
+            }
+            // Drop the children of a node before they are walked
+            if node.item == 6 {
+                node.take_children();
+            }
+        }
+
+        assert_eq!(visited, vec![0, 5, 6, 1, 4, 45, 2, 3, 40]);
+        assert_eq!(bush.bfs_items().copied().collect::<Vec<_>>(), visited);
+    }
+
+}
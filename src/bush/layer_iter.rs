@@ -0,0 +1,543 @@
+use std::iter::FusedIterator;
+use std::marker::PhantomData;
+use std::ptr;
+
+use super::{node_layer, null_node, BushNode, Layer};
+
+
+/// A run of nodes of a layer, walked from both ends until they meet
+struct RawRun<T> {
+
+    /// The next node from the front, null once the run is exhausted
+    front: *mut BushNode<T>,
+
+    /// The next node from the back, null until it's needed
+    back: *mut BushNode<T>,
+
+    /// Whether the front moves to the right
+    rightwards: bool,
+
+}
+
+
+impl<T> RawRun<T> {
+
+    /// Create a run from `front` to the end of its layer in the given direction
+    fn new(front: *mut BushNode<T>, rightwards: bool) -> Self {
+        Self {
+            front,
+            back: null_node(),
+            rightwards,
+        }
+    }
+
+
+    /// Create a run covering the whole layer from left to right
+    unsafe fn layer(layer: *const Layer<T>) -> Self {
+        Self {
+            front: (*layer).first,
+            back: (*layer).last,
+            rightwards: true,
+        }
+    }
+
+
+    unsafe fn step(&self, node: *mut BushNode<T>, forwards: bool) -> *mut BushNode<T> {
+        if forwards == self.rightwards {
+            (*node).right
+        } else {
+            (*node).left
+        }
+    }
+
+
+    /// Find the last node of the run, at the end of the layer
+    unsafe fn resolve_back(&mut self) {
+        let layer = node_layer(self.front);
+        self.back = if layer.is_null() {
+            // A detached run has no layer to look the end up in, so walk to it
+            let mut node = self.front;
+            loop {
+                let next = self.step(node, true);
+                if next.is_null() {
+                    break node;
+                }
+                node = next;
+            }
+        } else if self.rightwards {
+            (*layer).last
+        } else {
+            (*layer).first
+        };
+    }
+
+
+    /// Take the next node from the front, or null if the run is exhausted.
+    /// Only the links of the node are read, so the node can be lent out mutably right after.
+    unsafe fn next(&mut self) -> *mut BushNode<T> {
+        let node = self.front;
+        if node.is_null() {
+            return node;
+        }
+
+        if ptr::addr_eq(node, self.back) {
+            self.front = null_node();
+            self.back = null_node();
+        } else {
+            self.front = self.step(node, true);
+        }
+        node
+    }
+
+
+    /// Take the next node from the back, or null if the run is exhausted
+    unsafe fn next_back(&mut self) -> *mut BushNode<T> {
+        if self.front.is_null() {
+            return null_node();
+        }
+        if self.back.is_null() {
+            self.resolve_back();
+        }
+
+        let node = self.back;
+        if ptr::addr_eq(node, self.front) {
+            self.front = null_node();
+            self.back = null_node();
+        } else {
+            self.back = self.step(node, false);
+        }
+        node
+    }
+
+}
+
+
+impl<T> Clone for RawRun<T> {
+    fn clone(&self) -> Self {
+        Self {
+            front: self.front,
+            back: self.back,
+            rightwards: self.rightwards,
+        }
+    }
+}
+
+
+/// Generate the iterator traits for a layer iterator wrapping a `RawRun`,
+/// converting each node pointer into an item with `$yield`
+macro_rules! layer_iterator {
+    ($name:ident, $item:ty, |$node:ident| $yield:expr) => {
+
+        impl<'a, T> Iterator for $name<'a, T> {
+            type Item = $item;
+
+            fn next(&mut self) -> Option<Self::Item> {
+                let $node = unsafe { self.raw.next() };
+                if $node.is_null() {
+                    None
+                } else {
+                    unsafe { Some($yield) }
+                }
+            }
+        }
+
+
+        impl<'a, T> DoubleEndedIterator for $name<'a, T> {
+            fn next_back(&mut self) -> Option<Self::Item> {
+                let $node = unsafe { self.raw.next_back() };
+                if $node.is_null() {
+                    None
+                } else {
+                    unsafe { Some($yield) }
+                }
+            }
+        }
+
+
+        impl<'a, T> FusedIterator for $name<'a, T> {}
+
+    };
+}
+
+
+/// Iterator over the nodes to the right of a node, starting from the node itself
+pub struct BushNodeIterRight<'a, T> {
+
+    raw: RawRun<T>,
+    marker: PhantomData<&'a BushNode<T>>
+
+}
+
+
+/// Iterator over the nodes to the left of a node, starting from the node itself
+pub struct BushNodeIterLeft<'a, T> {
+
+    raw: RawRun<T>,
+    marker: PhantomData<&'a BushNode<T>>
+
+}
+
+
+/// Iterator over the items to the right of a node, starting from the node's own item
+pub struct BushNodeItemIterRight<'a, T> {
+
+    raw: RawRun<T>,
+    marker: PhantomData<&'a BushNode<T>>
+
+}
+
+
+/// Iterator over the items to the right of a node, starting from the node's own item
+pub struct BushNodeItemIterRightMut<'a, T> {
+
+    raw: RawRun<T>,
+    marker: PhantomData<&'a mut BushNode<T>>
+
+}
+
+
+/// Iterator over the items to the left of a node, starting from the node's own item
+pub struct BushNodeItemIterLeft<'a, T> {
+
+    raw: RawRun<T>,
+    marker: PhantomData<&'a BushNode<T>>
+
+}
+
+
+/// Iterator over the items to the left of a node, starting from the node's own item
+pub struct BushNodeItemIterLeftMut<'a, T> {
+
+    raw: RawRun<T>,
+    marker: PhantomData<&'a mut BushNode<T>>
+
+}
+
+
+layer_iterator!(BushNodeIterRight, &'a BushNode<T>, |node| &*node);
+layer_iterator!(BushNodeIterLeft, &'a BushNode<T>, |node| &*node);
+layer_iterator!(BushNodeItemIterRight, &'a T, |node| &(*node).item);
+layer_iterator!(BushNodeItemIterRightMut, &'a mut T, |node| &mut (*node).item);
+layer_iterator!(BushNodeItemIterLeft, &'a T, |node| &(*node).item);
+layer_iterator!(BushNodeItemIterLeftMut, &'a mut T, |node| &mut (*node).item);
+
+
+impl<'a, T> BushNodeIterRight<'a, T> {
+
+    pub(super) fn new(node: &'a BushNode<T>) -> Self {
+        Self {
+            raw: RawRun::new(node as *const BushNode<T> as *mut BushNode<T>, true),
+            marker: PhantomData
+        }
+    }
+
+}
+
+
+impl<'a, T> BushNodeIterLeft<'a, T> {
+
+    pub(super) fn new(node: &'a BushNode<T>) -> Self {
+        Self {
+            raw: RawRun::new(node as *const BushNode<T> as *mut BushNode<T>, false),
+            marker: PhantomData
+        }
+    }
+
+}
+
+
+impl<'a, T> BushNodeItemIterRightMut<'a, T> {
+
+    pub(super) fn new(node: &'a mut BushNode<T>) -> Self {
+        Self {
+            raw: RawRun::new(node, true),
+            marker: PhantomData
+        }
+    }
+
+}
+
+
+impl<'a, T> BushNodeItemIterLeftMut<'a, T> {
+
+    pub(super) fn new(node: &'a mut BushNode<T>) -> Self {
+        Self {
+            raw: RawRun::new(node, false),
+            marker: PhantomData
+        }
+    }
+
+}
+
+
+/// Iterator over the nodes of a whole layer, whose length is known
+pub struct BushLayerIter<'a, T> {
+
+    raw: RawRun<T>,
+    len: usize,
+    marker: PhantomData<&'a BushNode<T>>
+
+}
+
+
+/// Walk over the nodes of a whole layer, lending out one mutable node at a time.
+///
+/// A mutable node can insert siblings, which rewrites their links, so handing out mutable nodes through an
+/// `Iterator` would let two of them alias. The walk covers the nodes that were in the layer when it started, so the nodes inserted
+/// next to a lent node are not walked.
+pub struct BushLayerIterMut<'a, T> {
+
+    raw: RawRun<T>,
+    len: usize,
+    marker: PhantomData<&'a mut BushNode<T>>
+
+}
+
+
+/// Iterator over the mutable items of a whole layer, whose length is known
+pub struct BushLayerItemIterMut<'a, T> {
+
+    raw: RawRun<T>,
+    len: usize,
+    marker: PhantomData<&'a mut T>
+
+}
+
+
+impl<T> BushLayerIter<'_, T> {
+
+    pub(super) unsafe fn new(layer: *const Layer<T>) -> Self {
+        Self {
+            raw: RawRun::layer(layer),
+            len: (*layer).len,
+            marker: PhantomData
+        }
+    }
+
+}
+
+
+impl<T> BushLayerIterMut<'_, T> {
+
+    pub(super) unsafe fn new(layer: *const Layer<T>) -> Self {
+        Self {
+            raw: RawRun::layer(layer),
+            len: (*layer).len,
+            marker: PhantomData
+        }
+    }
+
+
+    /// Get the next node from the front of the walk
+    pub fn next_node(&mut self) -> Option<&mut BushNode<T>> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        unsafe { Some(&mut *self.raw.next()) }
+    }
+
+
+    /// Get the next node from the back of the walk
+    pub fn next_node_back(&mut self) -> Option<&mut BushNode<T>> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        unsafe { Some(&mut *self.raw.next_back()) }
+    }
+
+
+    /// Return the number of nodes left to walk
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+}
+
+
+impl<T> BushLayerItemIterMut<'_, T> {
+
+    pub(super) unsafe fn new(layer: *const Layer<T>) -> Self {
+        Self {
+            raw: RawRun::layer(layer),
+            len: (*layer).len,
+            marker: PhantomData
+        }
+    }
+
+}
+
+
+impl<T> Clone for BushLayerIter<'_, T> {
+    fn clone(&self) -> Self {
+        Self {
+            raw: self.raw.clone(),
+            len: self.len,
+            marker: PhantomData
+        }
+    }
+}
+
+
+impl<'a, T> Iterator for BushLayerIter<'a, T> {
+    type Item = &'a BushNode<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        unsafe { Some(&*self.raw.next()) }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+
+impl<T> DoubleEndedIterator for BushLayerIter<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        unsafe { Some(&*self.raw.next_back()) }
+    }
+}
+
+
+impl<T> ExactSizeIterator for BushLayerIter<'_, T> {}
+
+
+impl<T> FusedIterator for BushLayerIter<'_, T> {}
+
+
+impl<'a, T> Iterator for BushLayerItemIterMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        unsafe { Some(&mut (*self.raw.next()).item) }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+
+impl<T> DoubleEndedIterator for BushLayerItemIterMut<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        unsafe { Some(&mut (*self.raw.next_back()).item) }
+    }
+}
+
+
+impl<T> ExactSizeIterator for BushLayerItemIterMut<'_, T> {}
+
+
+impl<T> FusedIterator for BushLayerItemIterMut<'_, T> {}
+
+
+#[cfg(test)]
+mod tests {
+    use super::super::Bush;
+
+    #[test]
+    fn double_ended() {
+        let bush: Bush<i32> = (0..5).collect();
+
+        assert!(bush.iter_items().rev().copied().eq((0..5).rev()));
+        assert!(bush.iter_nodes_rev().map(|node| node.item).eq((0..5).rev()));
+
+        // Both ends meet in the middle without yielding a node twice
+        let mut nodes = bush.iter_items();
+        assert_eq!(nodes.len(), 5);
+        assert_eq!(nodes.next(), Some(&0));
+        assert_eq!(nodes.next_back(), Some(&4));
+        assert_eq!(nodes.next_back(), Some(&3));
+        assert_eq!(nodes.len(), 2);
+        assert_eq!(nodes.next(), Some(&1));
+        assert_eq!(nodes.next(), Some(&2));
+        assert_eq!(nodes.next_back(), None);
+        assert_eq!(nodes.next(), None);
+        assert_eq!(nodes.len(), 0);
+
+        assert_eq!(Bush::<i32>::new().iter_items().next_back(), None);
+    }
+
+
+    #[test]
+    fn double_ended_mut() {
+        let mut bush: Bush<i32> = (0..4).collect();
+
+        let mut items = bush.iter_items_mut();
+        let first = items.next().unwrap();
+        let last = items.next_back().unwrap();
+        *first += 10;
+        *last += 10;
+        assert_eq!(items.len(), 2);
+        for item in items.rev() {
+            *item *= -1;
+        }
+
+        assert_eq!(bush.iter_items().copied().collect::<Vec<_>>(), vec![10, -1, -2, 13]);
+    }
+
+
+    #[test]
+    fn from_node() {
+        let bush: Bush<i32> = (0..5).collect();
+        let middle = &bush[2];
+
+        assert_eq!(middle.iter_items_right().rev().copied().collect::<Vec<_>>(), vec![4, 3, 2]);
+        assert_eq!(middle.iter_items_left().rev().copied().collect::<Vec<_>>(), vec![0, 1, 2]);
+
+        let mut nodes = middle.iter_nodes_right();
+        assert_eq!(nodes.next_back().map(|node| node.item), Some(4));
+        assert_eq!(nodes.next().map(|node| node.item), Some(2));
+        assert_eq!(nodes.next().map(|node| node.item), Some(3));
+        assert!(nodes.next_back().is_none());
+        assert!(nodes.next().is_none());
+
+        let last = &bush[4];
+        let mut nodes = last.iter_nodes_right();
+        assert_eq!(nodes.next_back().map(|node| node.item), Some(4));
+        assert!(nodes.next().is_none());
+    }
+
+
+    #[test]
+    fn from_node_mut() {
+        let mut bush: Bush<i32> = (0..5).collect();
+
+        let mut items = bush[2].iter_items_right_mut();
+        let own = items.next().unwrap();
+        let last = items.next_back().unwrap();
+        *own += 10;
+        *last += 10;
+        assert_eq!(items.next().map(|item| *item), Some(3));
+        assert!(items.next().is_none());
+
+        for item in bush[1].iter_items_left_mut() {
+            *item *= -1;
+        }
+
+        assert_eq!(bush.iter_items().copied().collect::<Vec<_>>(), vec![0, -1, 12, 3, 14]);
+    }
+
+}
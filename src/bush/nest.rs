@@ -0,0 +1,197 @@
+use std::fmt;
+use std::error::Error;
+use std::ptr;
+
+use super::{move_run_to_children, Bush, BushNode};
+
+
+/// Reason why the delimiters of a layer could not be matched, with the positions of the offending items in the layer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NestError {
+
+    /// The opening item at `position` is never closed
+    Unclosed { position: usize },
+
+    /// The closing item at `position` has no opening item before it
+    Unopened { position: usize },
+
+    /// The closing item at `close` is of another kind than the opening item at `open` it would close
+    Mismatched { open: usize, close: usize },
+
+}
+
+
+impl fmt::Display for NestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NestError::Unclosed { position } => write!(f, "the opening item at position {position} is never closed"),
+            NestError::Unopened { position } => write!(f, "the closing item at position {position} has no opening item"),
+            NestError::Mismatched { open, close } => write!(f, "the closing item at position {close} does not match the opening item at position {open}"),
+        }
+    }
+}
+
+
+impl Error for NestError {}
+
+
+impl<T> Bush<T> {
+
+    /// Move the nodes between each opening item and its matching closing item into the children of the opening node.
+    /// The closing node stays right after its opening node, so that `flatten` undoes the nesting.
+    ///
+    /// Only the top layer is scanned, and the nodes are moved along with their branches.
+    /// If the delimiters don't match, the bush is left unchanged.
+    pub fn nest_by<O, C>(&mut self, open: O, close: C) -> Result<(), NestError>
+    where
+        O: Fn(&T) -> bool,
+        C: Fn(&T) -> bool
+    {
+        self.nest_by_kind(|item| open(item).then_some(()), |item| close(item).then_some(()))
+    }
+
+
+    /// Like `nest_by`, but the delimiters have a kind, and a closing item only matches an opening item of the same kind.
+    /// An item for which `open` returns a kind is never treated as a closing item.
+    pub fn nest_by_kind<K, O, C>(&mut self, open: O, close: C) -> Result<(), NestError>
+    where
+        K: PartialEq,
+        O: Fn(&T) -> Option<K>,
+        C: Fn(&T) -> Option<K>
+    {
+        // Check the delimiters before moving anything
+        let mut open_kinds: Vec<(usize, K)> = Vec::new();
+        for (position, item) in self.iter_items().enumerate() {
+            if let Some(kind) = open(item) {
+                open_kinds.push((position, kind));
+            } else if let Some(kind) = close(item) {
+                match open_kinds.pop() {
+                    Some((_, open_kind)) if open_kind == kind => {},
+                    Some((open, _)) => return Err(NestError::Mismatched { open, close: position }),
+                    None => return Err(NestError::Unopened { position })
+                }
+            }
+        }
+        if let Some(&(position, _)) = open_kinds.last() {
+            return Err(NestError::Unclosed { position });
+        }
+
+        let mut openers: Vec<*mut BushNode<T>> = Vec::new();
+        let mut node = self.layer().first;
+
+        while !node.is_null() {
+            unsafe {
+                let next = (*node).right;
+
+                if open(&(*node).item).is_some() {
+                    openers.push(node);
+                } else if close(&(*node).item).is_some() {
+                    let opener = openers.pop().expect("delimiters were checked");
+                    nest_between(opener, node);
+                }
+
+                node = next;
+            }
+        }
+
+        Ok(())
+    }
+
+}
+
+
+/// Move the nodes between `opener` and `closer` to the end of the children of `opener`.
+/// The opener is given children even if there is nothing to move, to tell an empty group from a leaf.
+unsafe fn nest_between<T>(opener: *mut BushNode<T>, closer: *mut BushNode<T>) {
+    let start = (*opener).right;
+    if ptr::addr_eq(start, closer) {
+        if (*opener).children.is_none() {
+            (*opener).set_children(Some(Bush::new()));
+        }
+    } else {
+        move_run_to_children(opener, start, (*closer).left);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{chars, render_with};
+
+
+    /// Classify opening brackets by their own kind
+    fn opening(item: &char) -> Option<char> {
+        matches!(item, '(' | '[').then_some(*item)
+    }
+
+
+    /// Classify closing brackets by the kind of their opening bracket
+    fn closing(item: &char) -> Option<char> {
+        match item {
+            ')' => Some('('),
+            ']' => Some('['),
+            _ => None
+        }
+    }
+
+
+    #[test]
+    fn nest_by() {
+        let mut bush = chars("a(b(c)d)()e");
+        bush.nest_by(|&item| item == '(', |&item| item == ')').unwrap();
+
+        assert_eq!(render_with(&bush, "{", "}", ""), "a({b({c})d})({})e");
+        assert_eq!(bush.total_node_count(), 11);
+        assert!(bush[1].children().unwrap()[1].parent_node().is_some_and(|parent| parent.item == '('));
+
+        // Flattening gives back the original layer
+        bush.flatten();
+        assert_eq!(bush.iter_items().collect::<String>(), "a(b(c)d)()e");
+    }
+
+
+    #[test]
+    fn nest_by_kind() {
+        let mut bush = chars("f[x(y)](z)");
+        bush.nest_by_kind(opening, closing).unwrap();
+
+        assert_eq!(render_with(&bush, "{", "}", ""), "f[{x({y})}]({z})");
+    }
+
+
+    #[test]
+    fn delimiter_errors() {
+
+        let mut bush = chars("a(b]");
+        assert_eq!(bush.nest_by_kind(opening, closing), Err(NestError::Mismatched { open: 1, close: 3 }));
+        // The bush is left unchanged on error
+        assert_eq!(render_with(&bush, "{", "}", ""), "a(b]");
+
+        let mut bush = chars("a)(");
+        assert_eq!(bush.nest_by_kind(opening, closing), Err(NestError::Unopened { position: 1 }));
+
+        let mut bush = chars("([x]");
+        assert_eq!(bush.nest_by_kind(opening, closing), Err(NestError::Unclosed { position: 0 }));
+        assert_eq!(bush.len(), 4);
+
+        assert_eq!(
+            NestError::Mismatched { open: 1, close: 3 }.to_string(),
+            "the closing item at position 3 does not match the opening item at position 1"
+        );
+    }
+
+
+    #[test]
+    fn nested_layer() {
+        let mut bush = chars("xy");
+        bush[1].set_children(Some(chars("(a)b")));
+
+        let mut children = bush[1].take_children().unwrap();
+        children.nest_by(|&item| item == '(', |&item| item == ')').unwrap();
+        bush[1].set_children(Some(children));
+        assert_eq!(render_with(&bush, "{", "}", ""), "xy{({a})b}");
+        assert_eq!(bush.total_node_count(), 6);
+    }
+
+}
@@ -0,0 +1,327 @@
+use std::collections::VecDeque;
+use std::iter::FusedIterator;
+use std::ops::RangeBounds;
+
+use super::{unlink_run, Bush, BushNode};
+
+
+impl<T> BushNode<T> {
+
+    /// Split the node into its item and its children
+    pub fn into_parts(mut self: Box<Self>) -> (T, Option<Bush<T>>) {
+        let children = self.take_children();
+        (self.into_sized().item, children)
+    }
+
+}
+
+
+/// An owning iterator over the nodes of a bush's top layer, along with their branches.
+/// The nodes that are not consumed are dropped with the iterator.
+pub struct IntoNodes<T> {
+
+    bush: Bush<T>,
+
+}
+
+
+impl<T> Iterator for IntoNodes<T> {
+    type Item = Box<BushNode<T>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.bush.pop_front()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.bush.len(), Some(self.bush.len()))
+    }
+}
+
+
+impl<T> DoubleEndedIterator for IntoNodes<T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.bush.pop_back()
+    }
+}
+
+
+impl<T> ExactSizeIterator for IntoNodes<T> {}
+
+
+impl<T> FusedIterator for IntoNodes<T> {}
+
+
+/// An owning depth first iterator over the items of a bush, yielding each item with its depth
+pub struct IntoDFS<T> {
+
+    /// The layers being consumed, the innermost last.
+    /// The depth of a layer is its position in the stack.
+    layers: Vec<Bush<T>>,
+
+}
+
+
+impl<T> Iterator for IntoDFS<T> {
+    type Item = (usize, T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let layer = self.layers.last_mut()?;
+            let Some(node) = layer.pop_front() else {
+                self.layers.pop();
+                continue;
+            };
+
+            let depth = self.layers.len() - 1;
+            let (item, children) = node.into_parts();
+            if let Some(children) = children {
+                self.layers.push(children);
+            }
+            return Some((depth, item));
+        }
+    }
+}
+
+
+impl<T> FusedIterator for IntoDFS<T> {}
+
+
+/// An owning breadth first iterator over the items of a bush, yielding each item with its depth
+pub struct IntoBFS<T> {
+
+    /// The layers left to consume, paired with their depth
+    layers: VecDeque<(usize, Bush<T>)>,
+
+}
+
+
+impl<T> Iterator for IntoBFS<T> {
+    type Item = (usize, T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (depth, layer) = self.layers.front_mut()?;
+            let depth = *depth;
+            let Some(node) = layer.pop_front() else {
+                self.layers.pop_front();
+                continue;
+            };
+
+            let (item, children) = node.into_parts();
+            if let Some(children) = children {
+                self.layers.push_back((depth + 1, children));
+            }
+            return Some((depth, item));
+        }
+    }
+}
+
+
+impl<T> FusedIterator for IntoBFS<T> {}
+
+
+/// Iterator removing the nodes of a layer that match a predicate, see `Bush::drain_filter`
+pub struct DrainFilter<'a, T, F>
+where
+    F: FnMut(&BushNode<T>) -> bool
+{
+
+    /// The next node to test, kept as a raw pointer so that the removed nodes can be unlinked
+    next: *mut BushNode<T>,
+    pred: F,
+
+    bush: &'a mut Bush<T>,
+
+}
+
+
+impl<T, F> Iterator for DrainFilter<'_, T, F>
+where
+    F: FnMut(&BushNode<T>) -> bool
+{
+    type Item = Box<BushNode<T>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.next.is_null() {
+            let node = self.next;
+            unsafe {
+                self.next = (*node).right;
+                if (self.pred)(&*node) {
+                    unlink_run(node, node);
+                    return Some(Box::from_raw(node));
+                }
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.bush.len()))
+    }
+}
+
+
+impl<T, F> FusedIterator for DrainFilter<'_, T, F>
+where
+    F: FnMut(&BushNode<T>) -> bool
+{}
+
+
+impl<T> Bush<T> {
+
+    /// Consume the bush into an iterator over the nodes of its top layer, along with their branches.
+    /// Use `BushNode::into_parts` to split each node into its item and its children.
+    pub fn into_nodes(self) -> IntoNodes<T> {
+        IntoNodes { bush: self }
+    }
+
+
+    /// Consume the bush into a depth first iterator over its items and their depth
+    pub fn into_dfs(self) -> IntoDFS<T> {
+        IntoDFS { layers: vec![self] }
+    }
+
+
+    /// Consume the bush into a breadth first iterator over its items and their depth
+    pub fn into_bfs(self) -> IntoBFS<T> {
+        IntoBFS { layers: VecDeque::from([(0, self)]) }
+    }
+
+
+    /// Remove the nodes in the given index range of the bush's top layer, returning them with their branches.
+    /// The nodes are removed right away, and the ones that are not consumed are dropped with the iterator.
+    /// Panics if the range is decreasing or goes past the end of the layer.
+    pub fn drain<R>(&mut self, range: R) -> IntoNodes<T>
+    where
+        R: RangeBounds<usize>
+    {
+        let (start, end) = self.layer_range(range, "drain");
+
+        if start == end {
+            return Bush::new().into_nodes();
+        }
+
+        let first = self.node_ptr(start);
+        let last = self.node_ptr(end - 1);
+        unsafe {
+            unlink_run(first, last);
+            Bush::from_run(first, last).into_nodes()
+        }
+    }
+
+
+    /// Get an iterator that removes the nodes of the bush's top layer for which `pred` returns true,
+    /// and yields them with their branches.
+    /// The nodes are tested as the iterator advances, so the nodes it doesn't reach are left in the bush.
+    pub fn drain_filter<F>(&mut self, pred: F) -> DrainFilter<'_, T, F>
+    where
+        F: FnMut(&BushNode<T>) -> bool
+    {
+        DrainFilter {
+            next: self.layer().first,
+            pred,
+            bush: self,
+        }
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+
+    const NESTED: &str = "0 [1 [2, 3], 4], 5, 6 [7]";
+
+
+    fn owned(items: &[(usize, &str)]) -> Vec<(usize, String)> {
+        items.iter().map(|&(depth, item)| (depth, item.to_string())).collect()
+    }
+
+
+    #[test]
+    fn into_nodes() {
+        let bush = test_util::bush(NESTED);
+
+        let mut nodes = bush.into_nodes();
+        assert_eq!(nodes.len(), 3);
+
+        let (item, children) = nodes.next().unwrap().into_parts();
+        assert_eq!(item, "0");
+        let children = children.unwrap();
+        assert_eq!(children.total_node_count(), 4);
+        assert!(children[0].parent_node().is_none());
+
+        assert_eq!(nodes.next_back().map(|node| node.item), Some("6".to_string()));
+        assert_eq!(nodes.len(), 1);
+    }
+
+
+    #[test]
+    fn into_dfs() {
+        let items: Vec<_> = test_util::bush(NESTED).into_dfs().collect();
+        assert_eq!(items, owned(&[(0, "0"), (1, "1"), (2, "2"), (2, "3"), (1, "4"), (0, "5"), (0, "6"), (1, "7")]));
+
+        // Dropping a partially consumed iterator frees the rest
+        let mut items = test_util::bush(NESTED).into_dfs();
+        assert_eq!(items.nth(2), Some((2, "2".to_string())));
+    }
+
+
+    #[test]
+    fn into_bfs() {
+        let items: Vec<_> = test_util::bush(NESTED).into_bfs().collect();
+        assert_eq!(items, owned(&[(0, "0"), (0, "5"), (0, "6"), (1, "1"), (1, "4"), (1, "7"), (2, "2"), (2, "3")]));
+
+        let mut items = test_util::bush(NESTED).into_bfs();
+        assert_eq!(items.nth(3), Some((1, "1".to_string())));
+    }
+
+
+    #[test]
+    fn drain() {
+        let mut bush: Bush<i32> = (0..6).collect();
+        bush[1].set_children(Some([10].into_iter().collect()));
+
+        let drained: Vec<_> = bush.drain(1..3).map(|node| node.into_parts()).collect();
+        assert_eq!(drained.len(), 2);
+        assert_eq!(drained[0].0, 1);
+        assert_eq!(drained[0].1.as_ref().and_then(Bush::first_item), Some(&10));
+        assert_eq!(bush.iter_items().copied().collect::<Vec<_>>(), vec![0, 3, 4, 5]);
+        assert_eq!(bush.total_node_count(), 4);
+
+        // The range is removed even if the iterator is dropped right away
+        bush.drain(2..);
+        assert_eq!(bush.iter_items().copied().collect::<Vec<_>>(), vec![0, 3]);
+        assert_eq!(bush.drain(..0).count(), 0);
+        assert_eq!(bush.drain(..=1).count(), 2);
+        assert!(bush.is_empty());
+    }
+
+
+    #[test]
+    #[should_panic(expected = "drain range end")]
+    fn drain_out_of_bounds() {
+        let mut bush: Bush<i32> = (0..3).collect();
+        bush.drain(1..4);
+    }
+
+
+    #[test]
+    fn drain_filter() {
+        let mut bush: Bush<i32> = (0..8).collect();
+        bush[2].set_children(Some([20].into_iter().collect()));
+
+        let evens: Vec<_> = bush.drain_filter(|node| node.item % 2 == 0).map(|node| node.item).collect();
+        assert_eq!(evens, vec![0, 2, 4, 6]);
+        assert_eq!(bush.iter_items().copied().collect::<Vec<_>>(), vec![1, 3, 5, 7]);
+        assert_eq!(bush.total_node_count(), 4);
+
+        // The nodes that are not reached stay in the bush
+        assert_eq!(bush.drain_filter(|_| true).next().map(|node| node.item), Some(1));
+        assert_eq!(bush.iter_items().copied().collect::<Vec<_>>(), vec![3, 5, 7]);
+        assert_eq!(bush.first_item(), Some(&3));
+    }
+
+}
@@ -0,0 +1,379 @@
+use std::ops::{Index, IndexMut};
+use std::ptr::null_mut;
+
+use super::index::layer_node;
+use super::{node_layer, null_node, unlink_run, Bush, BushNode, CheckedHandle, EmptyChildren, HandleError, Layer, NodeHandle};
+
+
+/// The position of a node in a bush, as the sequence of sibling indices leading to it.
+///
+/// The first index selects a node of the bush's top layer, each following index selects a node
+/// among the children of the previous one. The empty path doesn't address any node.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BushPath {
+
+    indices: Vec<usize>,
+
+}
+
+
+impl BushPath {
+
+    /// Create an empty path
+    pub fn new() -> BushPath {
+        Self::default()
+    }
+
+
+    /// Get the sibling indices of the path, starting from the top layer
+    pub fn indices(&self) -> &[usize] {
+        &self.indices
+    }
+
+
+    /// Return the number of indices in the path, which is one more than the depth of the node it addresses
+    pub fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+
+    /// Extend the path to the child at index `i` of the node it addresses
+    pub fn push(&mut self, i: usize) {
+        self.indices.push(i);
+    }
+
+
+    /// Remove and return the last index of the path, turning it into the path of the parent node
+    pub fn pop(&mut self) -> Option<usize> {
+        self.indices.pop()
+    }
+
+
+    /// Get the path of the parent node, or None if the path addresses a node of the top layer or is empty
+    pub fn parent(&self) -> Option<BushPath> {
+        match self.indices.split_last() {
+            Some((_, parent)) if !parent.is_empty() => Some(BushPath::from(parent)),
+            _ => None
+        }
+    }
+
+}
+
+
+impl From<Vec<usize>> for BushPath {
+    fn from(indices: Vec<usize>) -> Self {
+        Self { indices }
+    }
+}
+
+
+impl From<&[usize]> for BushPath {
+    fn from(indices: &[usize]) -> Self {
+        Self { indices: indices.to_vec() }
+    }
+}
+
+
+impl<const N: usize> From<[usize; N]> for BushPath {
+    fn from(indices: [usize; N]) -> Self {
+        Self { indices: indices.to_vec() }
+    }
+}
+
+
+impl FromIterator<usize> for BushPath {
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Self {
+        Self { indices: iter.into_iter().collect() }
+    }
+}
+
+
+impl<T> Bush<T> {
+
+    /// Get the node at the given path, or None if the path doesn't address a node of the bush
+    pub fn get_path(&self, path: &BushPath) -> Option<&BushNode<T>> {
+        let node = self.path_ptr(path.indices());
+        if node.is_null() {
+            None
+        } else {
+            unsafe { Some(&*node) }
+        }
+    }
+
+
+    /// Get the node at the given path, or None if the path doesn't address a node of the bush
+    pub fn get_path_mut(&mut self, path: &BushPath) -> Option<&mut BushNode<T>> {
+        let node = self.path_ptr(path.indices());
+        if node.is_null() {
+            None
+        } else {
+            unsafe { Some(&mut *node) }
+        }
+    }
+
+
+    /// Insert a new node at the given path, shifting its following siblings to the right.
+    /// The last index of the path is the insertion index among the children of the parent node,
+    /// which get a new children bush if they had none.
+    /// Panics if the parent node doesn't exist or the index is greater than the number of its children.
+    pub fn insert_at_path(&mut self, path: &BushPath, item: T) {
+        self.insert_node_at_path(path, BushNode::new(item));
+    }
+
+
+    /// Insert the given node and its branches at the given path, see `insert_at_path`.
+    pub fn insert_node_at_path(&mut self, path: &BushPath, node: Box<BushNode<T>>) {
+        let Some((&i, parent_path)) = path.indices().split_last() else {
+            panic!("cannot insert at an empty path");
+        };
+
+        if parent_path.is_empty() {
+            self.insert_node(i, node);
+            return;
+        }
+
+        let parent = self.path_ptr(parent_path);
+        assert!(!parent.is_null(), "insertion path {path:?} has no parent node in the bush");
+
+        unsafe {
+            if (*parent).children.is_none() {
+                assert!(i == 0, "insertion index (is {i}) should be <= len (is 0)");
+                (*parent).set_children(Some(Bush::new()));
+            }
+            if let Some(children) = &mut (*parent).children {
+                children.insert_node(i, node);
+            }
+        }
+    }
+
+
+    /// Remove the node at the given path and return it with its branches.
+    /// The parent node keeps its children bush even if it becomes empty.
+    /// Panics if the path doesn't address a node of the bush.
+    pub fn remove_at_path(&mut self, path: &BushPath) -> Box<BushNode<T>> {
+        let node = self.path_ptr(path.indices());
+        assert!(!node.is_null(), "path {path:?} doesn't address a node of the bush");
+
+        unsafe {
+            unlink_run(node, node);
+            Box::from_raw(node)
+        }
+    }
+
+
+    /// Remove the node at the given path and return it with its branches, or None if the path doesn't address a node.
+    /// If the parent node's children are left empty, `empty` decides whether it keeps them.
+    pub fn take_at_path(&mut self, path: &BushPath, empty: EmptyChildren) -> Option<Box<BushNode<T>>> {
+        let node = self.path_ptr(path.indices());
+        if node.is_null() {
+            return None;
+        }
+
+        unsafe {
+            self.remove_run(node, node, empty);
+            Some(Box::from_raw(node))
+        }
+    }
+
+
+    /// Compute the path of the node the given handle refers to, or None if the node is not in this bush.
+    ///
+    /// # Safety
+    ///
+    /// The handle must refer to a node that has not been removed. A node of another bush must not be mutably borrowed during the call.
+    pub unsafe fn path_of(&self, handle: NodeHandle<T>) -> Option<BushPath> {
+        let mut indices = Vec::new();
+        let mut node = handle.as_ptr();
+
+        loop {
+            let layer = node_layer(node);
+            if layer.is_null() {
+                return None;
+            }
+
+            indices.push((*node).iter_nodes_left().count() - 1);
+
+            if layer == self.layer.as_ptr() {
+                break;
+            }
+            node = (*layer).parent;
+            if node.is_null() {
+                return None;
+            }
+        }
+
+        indices.reverse();
+        Some(BushPath::from(indices))
+    }
+
+
+    /// Compute the path of the node the given checked handle refers to
+    pub fn try_path_of(&self, handle: &CheckedHandle<T>) -> Result<BushPath, HandleError> {
+        self.try_get(handle)?;
+        unsafe { self.path_of(handle.unchecked()) }.ok_or(HandleError::ForeignBush)
+    }
+
+
+    /// Resolve the given sibling indices to a node pointer, or null if they don't address a node
+    fn path_ptr(&self, indices: &[usize]) -> *mut BushNode<T> {
+        let mut layer: *const Layer<T> = self.layer.as_ptr();
+        let mut node = null_node();
+
+        for &i in indices {
+            if layer.is_null() {
+                return null_node();
+            }
+            unsafe {
+                node = layer_node(layer, i);
+                if node.is_null() {
+                    return null_node();
+                }
+                layer = match &(*node).children {
+                    Some(children) => children.layer.as_ptr(),
+                    None => null_mut()
+                };
+            }
+        }
+
+        node
+    }
+
+}
+
+
+impl<T> Index<&BushPath> for Bush<T> {
+    type Output = BushNode<T>;
+
+    fn index(&self, path: &BushPath) -> &Self::Output {
+        self.get_path(path).unwrap_or_else(|| panic!("path {path:?} doesn't address a node of the bush"))
+    }
+}
+
+
+impl<T> IndexMut<&BushPath> for Bush<T> {
+    fn index_mut(&mut self, path: &BushPath) -> &mut Self::Output {
+        self.get_path_mut(path).unwrap_or_else(|| panic!("path {path:?} doesn't address a node of the bush"))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+
+    const NESTED: &str = "0 [10 [100], 11], 1, 2 [20]";
+
+
+    #[test]
+    fn get_path() {
+        let mut bush = test_util::bush(NESTED);
+
+        assert_eq!(bush.get_path(&BushPath::from([0, 0, 0])).map(|node| node.item), Some(100));
+        assert_eq!(bush.get_path(&BushPath::from([0, 1])).map(|node| node.item), Some(11));
+        assert_eq!(bush[&BushPath::from([2, 0])].item, 20);
+        assert!(bush.get_path(&BushPath::new()).is_none());
+        assert!(bush.get_path(&BushPath::from([1, 0])).is_none());
+        assert!(bush.get_path(&BushPath::from([0, 2])).is_none());
+        assert!(bush.get_path(&BushPath::from([3])).is_none());
+
+        bush.get_path_mut(&BushPath::from([0, 0, 0])).unwrap().item = 101;
+        bush[&BushPath::from([1])].item = -1;
+        assert_eq!(bush.dfs_items().copied().collect::<Vec<_>>(), vec![0, 10, 101, 11, -1, 2, 20]);
+    }
+
+
+    #[test]
+    #[should_panic(expected = "doesn't address a node")]
+    fn index_missing_path() {
+        let bush: Bush<i32> = test_util::bush(NESTED);
+        let _ = &bush[&BushPath::from([1, 0])];
+    }
+
+
+    #[test]
+    fn insert_at_path() {
+        let mut bush = test_util::bush(NESTED);
+
+        bush.insert_at_path(&BushPath::from([0, 1]), 12);
+        bush.insert_at_path(&BushPath::from([1, 0]), 30);
+        bush.insert_at_path(&BushPath::from([3]), 3);
+        bush.insert_at_path(&BushPath::from([2, 0, 0]), 200);
+
+        assert_eq!(bush.dfs_items().copied().collect::<Vec<_>>(), vec![0, 10, 100, 12, 11, 1, 30, 2, 20, 200, 3]);
+        assert_eq!(bush[&BushPath::from([1, 0])].parent_item(), Some(&1));
+        assert_eq!(bush.total_node_count(), 11);
+    }
+
+
+    #[test]
+    #[should_panic(expected = "has no parent node")]
+    fn insert_without_parent() {
+        let mut bush = test_util::bush(NESTED);
+        bush.insert_at_path(&BushPath::from([5, 0]), 0);
+    }
+
+
+    #[test]
+    fn remove_at_path() {
+        let mut bush = test_util::bush(NESTED);
+
+        let node = bush.remove_at_path(&BushPath::from([0, 0]));
+        assert_eq!(node.item, 10);
+        assert_eq!(node.children().and_then(Bush::first_item), Some(&100));
+        assert_eq!(bush.dfs_items().copied().collect::<Vec<_>>(), vec![0, 11, 1, 2, 20]);
+
+        assert_eq!(bush.remove_at_path(&BushPath::from([2, 0])).item, 20);
+        assert!(bush[2].children().unwrap().is_empty());
+        assert_eq!(bush.total_node_count(), 4);
+    }
+
+
+    #[test]
+    fn path_of_handle() {
+        let bush: Bush<i32> = test_util::bush(NESTED);
+
+        for path in [vec![0], vec![0, 0], vec![0, 0, 0], vec![0, 1], vec![1], vec![2, 0]] {
+            let path = BushPath::from(path);
+            let handle = bush[&path].into_handle();
+            assert_eq!(unsafe { bush.path_of(handle) }, Some(path));
+        }
+
+        // Paths are relative to the bush they are computed on
+        let children = bush[0].children().unwrap();
+        let handle = bush[&BushPath::from([0, 0, 0])].into_handle();
+        assert_eq!(unsafe { children.path_of(handle) }, Some(BushPath::from([0, 0])));
+        assert_eq!(unsafe { children.path_of(bush[1].into_handle()) }, None);
+
+        let other = test_util::bush(NESTED);
+        assert_eq!(unsafe { bush.path_of(other[0].into_handle()) }, None);
+    }
+
+
+    #[test]
+    fn path_of_checked_handle() {
+        let mut bush: Bush<i32> = test_util::bush(NESTED);
+        let handle = bush.checked_handle(&bush[&BushPath::from([0, 1])]).unwrap();
+
+        assert_eq!(bush.try_path_of(&handle), Ok(BushPath::from([0, 1])));
+        bush.remove_at_path(&BushPath::from([1]));
+        assert_eq!(bush.try_path_of(&handle), Err(HandleError::StaleHandle));
+    }
+
+
+    #[test]
+    fn path_parent() {
+        let mut path = BushPath::from([1, 2, 3]);
+        assert_eq!(path.parent(), Some(BushPath::from([1, 2])));
+        assert_eq!(path.pop(), Some(3));
+        path.push(4);
+        assert_eq!(path.indices(), &[1, 2, 4]);
+        assert_eq!(BushPath::from([1]).parent(), None);
+    }
+
+}
@@ -0,0 +1,289 @@
+use std::fmt;
+use std::error::Error;
+
+use super::{move_run_to_children, Bush, BushNode};
+
+
+/// How an infix operator groups with operators of the same precedence
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Associativity {
+
+    /// `a - b - c` is `(a - b) - c`
+    Left,
+
+    /// `a ^ b ^ c` is `a ^ (b ^ c)`
+    Right,
+
+}
+
+
+/// The role of an item in an expression, as told by the classifier of `Bush::reduce_operators`.
+/// Operators with a higher precedence bind tighter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fixity {
+
+    /// An operator taking the operand to its right
+    Prefix { precedence: u32 },
+
+    /// An operator taking the operands on both sides
+    Infix { precedence: u32, associativity: Associativity },
+
+    /// An operator taking the operand to its left
+    Postfix { precedence: u32 },
+
+    /// A value, taken as is
+    Operand,
+
+}
+
+
+/// Reason why a layer is not a well formed expression, with the position of the offending item in the layer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrecedenceError {
+
+    /// The operator at `position` lacks one of its operands
+    MissingOperand { position: usize },
+
+    /// The operand or prefix operator at `position` follows a complete operand without an operator in between
+    UnexpectedOperand { position: usize },
+
+}
+
+
+impl fmt::Display for PrecedenceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PrecedenceError::MissingOperand { position } => write!(f, "the operator at position {position} is missing an operand"),
+            PrecedenceError::UnexpectedOperand { position } => write!(f, "expected an operator at position {position}"),
+        }
+    }
+}
+
+
+impl Error for PrecedenceError {}
+
+
+/// An operator waiting for its right operand, or for its reduction
+struct PendingOperator {
+
+    position: usize,
+    precedence: u32,
+    infix: bool,
+
+}
+
+
+/// An operator to give its operands to, in the order they were found
+struct Reduction {
+
+    operator: usize,
+    operands: [usize; 2],
+    count: usize,
+
+}
+
+
+/// Reduce the topmost pending operator, taking its operands from the operand stack
+fn reduce(operators: &mut Vec<PendingOperator>, operands: &mut Vec<usize>, reductions: &mut Vec<Reduction>) {
+    let operator = operators.pop().expect("an operator is pending");
+
+    // The state machine guarantees the operands are there
+    let right = operands.pop().expect("the operator has its right operand");
+    let reduction = if operator.infix {
+        let left = operands.pop().expect("the operator has its left operand");
+        Reduction { operator: operator.position, operands: [left, right], count: 2 }
+    } else {
+        Reduction { operator: operator.position, operands: [right, 0], count: 1 }
+    };
+
+    reductions.push(reduction);
+    operands.push(operator.position);
+}
+
+
+/// Find the reductions turning the items of a layer into one expression, with the shunting yard algorithm
+fn plan_reductions(fixities: &[Fixity]) -> Result<Vec<Reduction>, PrecedenceError> {
+    let mut operators: Vec<PendingOperator> = Vec::new();
+    let mut operands: Vec<usize> = Vec::new();
+    let mut reductions: Vec<Reduction> = Vec::new();
+    let mut expect_operand = true;
+
+    for (position, &fixity) in fixities.iter().enumerate() {
+        match (expect_operand, fixity) {
+
+            (true, Fixity::Operand) => {
+                operands.push(position);
+                expect_operand = false;
+            },
+
+            (true, Fixity::Prefix { precedence }) => {
+                operators.push(PendingOperator { position, precedence, infix: false });
+            },
+
+            (true, Fixity::Infix { .. } | Fixity::Postfix { .. }) => {
+                return Err(PrecedenceError::MissingOperand { position });
+            },
+
+            (false, Fixity::Operand | Fixity::Prefix { .. }) => {
+                return Err(PrecedenceError::UnexpectedOperand { position });
+            },
+
+            (false, Fixity::Postfix { precedence }) => {
+                while operators.last().is_some_and(|top| top.precedence >= precedence) {
+                    reduce(&mut operators, &mut operands, &mut reductions);
+                }
+                let operand = operands.pop().expect("a complete operand precedes the operator");
+                reductions.push(Reduction { operator: position, operands: [operand, 0], count: 1 });
+                operands.push(position);
+            },
+
+            (false, Fixity::Infix { precedence, associativity }) => {
+                while operators.last().is_some_and(|top| {
+                    top.precedence > precedence || (top.precedence == precedence && associativity == Associativity::Left)
+                }) {
+                    reduce(&mut operators, &mut operands, &mut reductions);
+                }
+                operators.push(PendingOperator { position, precedence, infix: true });
+                expect_operand = true;
+            },
+
+        }
+    }
+
+    if expect_operand && !fixities.is_empty() {
+        // The last item is an operator still waiting for its right operand
+        return Err(PrecedenceError::MissingOperand { position: fixities.len() - 1 });
+    }
+
+    while !operators.is_empty() {
+        reduce(&mut operators, &mut operands, &mut reductions);
+    }
+
+    Ok(reductions)
+}
+
+
+impl<T> Bush<T> {
+
+    /// Turn the top layer of the bush into a single expression node, where each operator owns its operands as children.
+    /// `classify` tells the role of each item. Prefix and postfix operators get one child, infix operators get their
+    /// left operand followed by their right one. The operands are appended to the children an operator may already have,
+    /// and are moved along with their branches.
+    ///
+    /// To reduce a nested layer, call this on the children of its parent node.
+    /// If the layer is not a well formed expression, the bush is left unchanged.
+    pub fn reduce_operators<F>(&mut self, classify: F) -> Result<(), PrecedenceError>
+    where
+        F: Fn(&T) -> Fixity
+    {
+        let fixities: Vec<Fixity> = self.iter_items().map(classify).collect();
+        let reductions = plan_reductions(&fixities)?;
+
+        let mut nodes: Vec<*mut BushNode<T>> = Vec::with_capacity(fixities.len());
+        let mut node = self.layer().first;
+        while !node.is_null() {
+            nodes.push(node);
+            node = unsafe { (*node).right };
+        }
+
+        // Operands are reduced before the operators that take them, so each node is moved along with its own operands
+        for reduction in reductions {
+            let operator = nodes[reduction.operator];
+            for &operand in &reduction.operands[..reduction.count] {
+                let operand = nodes[operand];
+                unsafe {
+                    move_run_to_children(operator, operand, operand);
+                }
+            }
+        }
+
+        debug_assert!(self.len() <= 1);
+        Ok(())
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::super::BushPath;
+    use super::*;
+    use crate::test_util::{chars, render_with};
+
+    /// Arithmetic with `~` as negation, `!` as factorial and `^` as right associative power
+    fn arithmetic(item: &char) -> Fixity {
+        match item {
+            '+' | '-' => Fixity::Infix { precedence: 1, associativity: Associativity::Left },
+            '*' | '/' => Fixity::Infix { precedence: 2, associativity: Associativity::Left },
+            '~' => Fixity::Prefix { precedence: 3 },
+            '^' => Fixity::Infix { precedence: 4, associativity: Associativity::Right },
+            '!' => Fixity::Postfix { precedence: 5 },
+            _ => Fixity::Operand
+        }
+    }
+
+
+    fn reduced(text: &str) -> Result<String, PrecedenceError> {
+        let mut bush = chars(text);
+        bush.reduce_operators(arithmetic)?;
+        Ok(render_with(&bush, "(", ")", ""))
+    }
+
+
+    #[test]
+    fn precedence_and_associativity() {
+        assert_eq!(reduced("a+b*c").unwrap(), "+(a*(bc))");
+        assert_eq!(reduced("a*b+c").unwrap(), "+(*(ab)c)");
+        assert_eq!(reduced("a-b-c").unwrap(), "-(-(ab)c)");
+        assert_eq!(reduced("a^b^c").unwrap(), "^(a^(bc))");
+        assert_eq!(reduced("a").unwrap(), "a");
+        assert_eq!(reduced("").unwrap(), "");
+    }
+
+
+    #[test]
+    fn prefix_and_postfix() {
+        assert_eq!(reduced("~a*b").unwrap(), "*(~(a)b)");
+        assert_eq!(reduced("~a^b").unwrap(), "~(^(ab))");
+        assert_eq!(reduced("~~a!").unwrap(), "~(~(!(a)))");
+        assert_eq!(reduced("a!!+b").unwrap(), "+(!(!(a))b)");
+        assert_eq!(reduced("a*~b").unwrap(), "*(a~(b))");
+    }
+
+
+    #[test]
+    fn missing_operands() {
+        assert_eq!(reduced("a+"), Err(PrecedenceError::MissingOperand { position: 1 }));
+        assert_eq!(reduced("*a"), Err(PrecedenceError::MissingOperand { position: 0 }));
+        assert_eq!(reduced("a+*b"), Err(PrecedenceError::MissingOperand { position: 2 }));
+        assert_eq!(reduced("~"), Err(PrecedenceError::MissingOperand { position: 0 }));
+        assert_eq!(reduced("!a"), Err(PrecedenceError::MissingOperand { position: 0 }));
+        assert_eq!(reduced("a b"), Err(PrecedenceError::UnexpectedOperand { position: 1 }));
+        assert_eq!(reduced("a~b"), Err(PrecedenceError::UnexpectedOperand { position: 1 }));
+
+        // The bush is left unchanged on error
+        let mut bush = chars("a+b*");
+        assert!(bush.reduce_operators(arithmetic).is_err());
+        assert_eq!(render_with(&bush, "(", ")", ""), "a+b*");
+    }
+
+
+    #[test]
+    fn nested_layer() {
+        // `f` owns the argument `x*y+z`, whose operand `x` has branches of its own
+        let mut bush = chars("f;");
+        bush[0].set_children(Some(chars("x*y+z")));
+        bush[&BushPath::from([0, 0])].set_children(Some(chars("12")));
+
+        let mut argument = bush[0].take_children().unwrap();
+        argument.reduce_operators(arithmetic).unwrap();
+        bush[0].set_children(Some(argument));
+
+        assert_eq!(render_with(&bush, "(", ")", ""), "f(+(*(x(12)y)z));");
+        assert_eq!(bush.total_node_count(), 9);
+        let sum = &bush[0].children().unwrap()[0];
+        assert_eq!(sum.parent_node().map(|node| node.item), Some('f'));
+        assert_eq!(sum.children().unwrap()[0].parent_node().map(|node| node.item), Some('+'));
+    }
+
+}
@@ -0,0 +1,293 @@
+use std::fmt;
+use std::error::Error;
+use std::ptr;
+
+use super::index::layer_node;
+use super::{link_run, node_layer, unlink_run, Bush, BushNode, NodeHandle};
+
+
+/// Reason why a node could not be moved
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveError {
+
+    /// One of the nodes is not in the bush
+    NotInBush,
+
+    /// The destination is inside the branches of the node being moved
+    IntoOwnSubtree,
+
+}
+
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            MoveError::NotInBush => "the node is not in the bush",
+            MoveError::IntoOwnSubtree => "the node cannot be moved into its own branches",
+        })
+    }
+}
+
+
+impl Error for MoveError {}
+
+
+/// Whether `node` is `root` or one of its descendants
+unsafe fn in_subtree<T>(root: *const BushNode<T>, mut node: *const BushNode<T>) -> bool {
+    while !node.is_null() {
+        if ptr::addr_eq(node, root) {
+            return true;
+        }
+        let layer = node_layer(node);
+        if layer.is_null() {
+            return false;
+        }
+        node = (*layer).parent;
+    }
+    false
+}
+
+
+impl<T> Bush<T> {
+
+    /// Check that `node` is in the bush and can be moved next to or into `destination`
+    unsafe fn check_move(&self, node: *const BushNode<T>, destination: *const BushNode<T>) -> Result<(), MoveError> {
+        if !self.owns_node(node) || !self.owns_node(destination) {
+            return Err(MoveError::NotInBush);
+        }
+        if !ptr::addr_eq(node, destination) && in_subtree(node, destination) {
+            return Err(MoveError::IntoOwnSubtree);
+        }
+        Ok(())
+    }
+
+
+    /// Move the node, with its branches, right before `target`. Both nodes can be in any layer of the bush.
+    ///
+    /// # Safety
+    ///
+    /// Both handles must refer to nodes that have not been removed. A node of another bush must not be mutably borrowed during the call.
+    /// Nodes of other bushes are rejected with `MoveError::NotInBush`.
+    pub unsafe fn move_before(&mut self, node: NodeHandle<T>, target: NodeHandle<T>) -> Result<(), MoveError> {
+        let node = node.as_ptr() as *mut BushNode<T>;
+        let target = target.as_ptr() as *mut BushNode<T>;
+        self.check_move(node, target)?;
+
+        if !ptr::addr_eq(node, target) {
+            unlink_run(node, node);
+            link_run(node_layer(target), (*target).left, target, node, node);
+        }
+        Ok(())
+    }
+
+
+    /// Move the node, with its branches, right after `target`. Both nodes can be in any layer of the bush.
+    ///
+    /// # Safety
+    ///
+    /// Both handles must refer to nodes that have not been removed. A node of another bush must not be mutably borrowed during the call.
+    /// Nodes of other bushes are rejected with `MoveError::NotInBush`.
+    pub unsafe fn move_after(&mut self, node: NodeHandle<T>, target: NodeHandle<T>) -> Result<(), MoveError> {
+        let node = node.as_ptr() as *mut BushNode<T>;
+        let target = target.as_ptr() as *mut BushNode<T>;
+        self.check_move(node, target)?;
+
+        if !ptr::addr_eq(node, target) {
+            unlink_run(node, node);
+            link_run(node_layer(target), target, (*target).right, node, node);
+        }
+        Ok(())
+    }
+
+
+    /// Move the node, with its branches, to index `position` of the children of `parent`, giving it children if it has none.
+    /// The index is taken after the node is removed from its layer, in case it's already a child of `parent`.
+    ///
+    /// Panics if `position` is greater than the number of children left.
+    ///
+    /// # Safety
+    ///
+    /// Both handles must refer to nodes that have not been removed. A node of another bush must not be mutably borrowed during the call.
+    /// Nodes of other bushes are rejected with `MoveError::NotInBush`.
+    pub unsafe fn move_into_children(&mut self, node: NodeHandle<T>, parent: NodeHandle<T>, position: usize) -> Result<(), MoveError> {
+        let node = node.as_ptr() as *mut BushNode<T>;
+        let parent = parent.as_ptr() as *mut BushNode<T>;
+        self.check_move(node, parent)?;
+        if ptr::addr_eq(node, parent) {
+            return Err(MoveError::IntoOwnSubtree);
+        }
+
+        let len = match &(*parent).children {
+            Some(children) if node_layer(node) == children.layer.as_ptr() => children.len() - 1,
+            Some(children) => children.len(),
+            None => 0
+        };
+        assert!(position <= len, "insertion index (is {position}) should be <= len (is {len})");
+
+        unlink_run(node, node);
+
+        if (*parent).children.is_none() {
+            (*parent).set_children(Some(Bush::new()));
+        }
+        if let Some(children) = &(*parent).children {
+            let layer = children.layer.as_ptr();
+            let right = layer_node(layer, position);
+            let left = if right.is_null() { (*layer).last } else { (*right).left };
+            link_run(layer, left, right, node, node);
+        }
+        Ok(())
+    }
+
+
+    /// Swap the positions of two nodes, along with their branches. The nodes can be in any layers of the bush,
+    /// as long as neither is in the branches of the other.
+    ///
+    /// # Safety
+    ///
+    /// Both handles must refer to nodes that have not been removed. A node of another bush must not be mutably borrowed during the call.
+    /// Nodes of other bushes are rejected with `MoveError::NotInBush`.
+    pub unsafe fn swap_nodes(&mut self, a: NodeHandle<T>, b: NodeHandle<T>) -> Result<(), MoveError> {
+        let a = a.as_ptr() as *mut BushNode<T>;
+        let b = b.as_ptr() as *mut BushNode<T>;
+        self.check_move(a, b)?;
+        self.check_move(b, a)?;
+
+        if ptr::addr_eq(a, b) {
+            return Ok(());
+        }
+
+        // Adjacent nodes are swapped by moving one past the other
+        if ptr::addr_eq((*a).right, b) {
+            unlink_run(a, a);
+            link_run(node_layer(b), b, (*b).right, a, a);
+        } else if ptr::addr_eq((*b).right, a) {
+            unlink_run(b, b);
+            link_run(node_layer(a), a, (*a).right, b, b);
+        } else {
+            let (layer_a, left_a, right_a) = (node_layer(a), (*a).left, (*a).right);
+            let (layer_b, left_b, right_b) = (node_layer(b), (*b).left, (*b).right);
+            unlink_run(a, a);
+            unlink_run(b, b);
+            link_run(layer_b, left_b, right_b, a, a);
+            link_run(layer_a, left_a, right_a, b, b);
+        }
+        Ok(())
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{self, handle, render};
+
+    const NESTED: &str = "a [b [c], d], e, f [g]";
+
+
+    /// Check that the layers can be walked both ways and that every node knows its parent
+    fn assert_consistent(bush: &Bush<char>) {
+        let forwards: Vec<char> = bush.iter_items().copied().collect();
+        let mut backwards: Vec<char> = bush.iter_items().rev().copied().collect();
+        backwards.reverse();
+        assert_eq!(forwards, backwards);
+        assert_eq!(bush.len(), forwards.len());
+
+        for node in bush.iter_nodes() {
+            if let Some(children) = node.children() {
+                assert_consistent(children);
+                assert!(children.iter_nodes().all(|child| child.parent_node().is_some_and(|parent| std::ptr::eq(parent, node))));
+            }
+        }
+    }
+
+
+    #[test]
+    fn move_before_and_after() {
+        let mut bush = test_util::bush(NESTED);
+
+        unsafe { bush.move_before(handle(&bush, 'b'), handle(&bush, 'a')) }.unwrap();
+        assert_eq!(render(&bush), "b[c]a[d]ef[g]");
+
+        unsafe { bush.move_after(handle(&bush, 'e'), handle(&bush, 'g')) }.unwrap();
+        assert_eq!(render(&bush), "b[c]a[d]f[ge]");
+
+        unsafe { bush.move_after(handle(&bush, 'b'), handle(&bush, 'f')) }.unwrap();
+        assert_eq!(render(&bush), "a[d]f[ge]b[c]");
+        assert_eq!(bush.first_item(), Some(&'a'));
+        assert_eq!(bush.last_item(), Some(&'b'));
+        assert_eq!(bush.total_node_count(), 7);
+        assert_consistent(&bush);
+
+        // Moving a node next to itself leaves it in place
+        unsafe { bush.move_before(handle(&bush, 'f'), handle(&bush, 'f')) }.unwrap();
+        assert_eq!(render(&bush), "a[d]f[ge]b[c]");
+    }
+
+
+    #[test]
+    fn move_into_children() {
+        let mut bush = test_util::bush(NESTED);
+
+        unsafe { bush.move_into_children(handle(&bush, 'e'), handle(&bush, 'c'), 0) }.unwrap();
+        assert_eq!(render(&bush), "a[b[c[e]]d]f[g]");
+
+        unsafe { bush.move_into_children(handle(&bush, 'f'), handle(&bush, 'a'), 1) }.unwrap();
+        assert_eq!(render(&bush), "a[b[c[e]]f[g]d]");
+
+        // The position is taken after the node leaves its layer
+        unsafe { bush.move_into_children(handle(&bush, 'b'), handle(&bush, 'a'), 2) }.unwrap();
+        assert_eq!(render(&bush), "a[f[g]db[c[e]]]");
+        assert_eq!(bush.total_node_count(), 7);
+        assert_consistent(&bush);
+    }
+
+
+    #[test]
+    #[should_panic(expected = "insertion index")]
+    fn move_into_children_out_of_bounds() {
+        let mut bush = test_util::bush(NESTED);
+        let _ = unsafe { bush.move_into_children(handle(&bush, 'e'), handle(&bush, 'f'), 2) };
+    }
+
+
+    #[test]
+    fn swap_nodes() {
+        let mut bush = test_util::bush(NESTED);
+
+        unsafe { bush.swap_nodes(handle(&bush, 'a'), handle(&bush, 'e')) }.unwrap();
+        assert_eq!(render(&bush), "ea[b[c]d]f[g]");
+
+        unsafe { bush.swap_nodes(handle(&bush, 'f'), handle(&bush, 'a')) }.unwrap();
+        assert_eq!(render(&bush), "ef[g]a[b[c]d]");
+
+        unsafe { bush.swap_nodes(handle(&bush, 'g'), handle(&bush, 'b')) }.unwrap();
+        assert_eq!(render(&bush), "ef[b[c]]a[gd]");
+
+        unsafe { bush.swap_nodes(handle(&bush, 'e'), handle(&bush, 'a')) }.unwrap();
+        assert_eq!(render(&bush), "a[gd]f[b[c]]e");
+        assert_eq!(bush.first_item(), Some(&'a'));
+        assert_eq!(bush.last_item(), Some(&'e'));
+        assert_eq!(bush.total_node_count(), 7);
+        assert_consistent(&bush);
+    }
+
+
+    #[test]
+    fn rejected_moves() {
+        let mut bush = test_util::bush(NESTED);
+
+        assert_eq!(unsafe { bush.move_into_children(handle(&bush, 'a'), handle(&bush, 'c'), 0) }, Err(MoveError::IntoOwnSubtree));
+        assert_eq!(unsafe { bush.move_into_children(handle(&bush, 'a'), handle(&bush, 'a'), 0) }, Err(MoveError::IntoOwnSubtree));
+        assert_eq!(unsafe { bush.move_after(handle(&bush, 'b'), handle(&bush, 'c')) }, Err(MoveError::IntoOwnSubtree));
+        assert_eq!(unsafe { bush.swap_nodes(handle(&bush, 'd'), handle(&bush, 'a')) }, Err(MoveError::IntoOwnSubtree));
+
+        let other = test_util::bush(NESTED);
+        assert_eq!(unsafe { bush.move_before(handle(&bush, 'a'), handle(&other, 'e')) }, Err(MoveError::NotInBush));
+        assert_eq!(unsafe { bush.swap_nodes(handle(&other, 'a'), handle(&bush, 'e')) }, Err(MoveError::NotInBush));
+
+        // Nothing moved
+        assert_eq!(render(&bush), "a[b[c]d]ef[g]");
+    }
+
+}
@@ -0,0 +1,227 @@
+use std::ptr;
+
+use super::{node_layer, unlink_run, Bush, BushNode, NodeHandle};
+
+
+/// What to do with a children bush left empty by a removal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmptyChildren {
+
+    /// Keep the empty children bush, so the parent can still be told apart from a leaf
+    Keep,
+
+    /// Drop the empty children bush, leaving the parent without children
+    Collapse,
+
+}
+
+
+impl<T> Bush<T> {
+
+    /// Unlink the run of nodes from `start` to `end` from whatever layer of the bush it's in.
+    /// If the layer is left empty and belongs to a node, `empty` decides whether the node keeps it.
+    /// The bush's own top layer is never collapsed, even when it is the children of a node in an enclosing bush.
+    pub(super) unsafe fn remove_run(&mut self, start: *mut BushNode<T>, end: *mut BushNode<T>, empty: EmptyChildren) {
+        let layer = node_layer(start);
+        unlink_run(start, end);
+
+        if empty == EmptyChildren::Collapse && (*layer).len == 0 && layer != self.layer.as_ptr() {
+            let parent = (*layer).parent;
+            if !parent.is_null() {
+                (*parent).take_children();
+            }
+        }
+    }
+
+
+    /// Remove the given node from any layer of the bush and return it with its branches.
+    /// Panics if the node is not in the bush.
+    ///
+    /// # Safety
+    ///
+    /// The handle must refer to a node that has not been removed. A node of another bush must not be mutably borrowed during the call.
+    /// See `try_remove_node` for a checked handle.
+    pub unsafe fn remove_node(&mut self, node: NodeHandle<T>, empty: EmptyChildren) -> Box<BushNode<T>> {
+        let node = node.as_ptr() as *mut BushNode<T>;
+        assert!(self.owns_node(node), "the node is not in the bush");
+
+        self.remove_run(node, node, empty);
+        Box::from_raw(node)
+    }
+
+
+    /// Remove the run of sibling nodes from `start` to `end` from any layer of the bush,
+    /// and return a bush holding them with their branches.
+    /// Panics if the nodes are not in the bush, or if `end` is not `start` or a node to its right.
+    ///
+    /// # Safety
+    ///
+    /// Both handles must refer to nodes that have not been removed. A node of another bush must not be mutably borrowed during the call.
+    pub unsafe fn remove_slice(&mut self, start: NodeHandle<T>, end: NodeHandle<T>, empty: EmptyChildren) -> Bush<T> {
+        let start = start.as_ptr() as *mut BushNode<T>;
+        let end = end.as_ptr() as *mut BushNode<T>;
+        assert!(self.owns_node(start), "the start node is not in the bush");
+
+        let mut node = start;
+        while !ptr::addr_eq(node, end) {
+            assert!(!node.is_null(), "the end node does not follow the start node in the layer");
+            node = (*node).right;
+        }
+
+        self.remove_run(start, end, empty);
+        Bush::from_run(start, end)
+    }
+
+
+    /// Remove every node of the bush, at any depth, for which `pred` returns true, and return them with their branches.
+    /// The nodes are tested in depth first order, and the branches of a removed node are not tested.
+    pub fn remove_where<F>(&mut self, mut pred: F, empty: EmptyChildren) -> Vec<Box<BushNode<T>>>
+    where
+        F: FnMut(&BushNode<T>) -> bool
+    {
+        let mut removed = Vec::new();
+
+        // The nodes left to test, the next one last
+        let mut pending: Vec<*mut BushNode<T>> = Vec::new();
+        if !self.layer().first.is_null() {
+            pending.push(self.layer().first);
+        }
+
+        while let Some(node) = pending.pop() {
+            unsafe {
+                let right = (*node).right;
+                if !right.is_null() {
+                    pending.push(right);
+                }
+
+                if pred(&*node) {
+                    // A layer can only be left empty by its last node, so no pending node is in a dropped layer
+                    self.remove_run(node, node, empty);
+                    removed.push(Box::from_raw(node));
+                } else if let Some(children) = &(*node).children {
+                    let first = children.layer().first;
+                    if !first.is_null() {
+                        pending.push(first);
+                    }
+                }
+            }
+        }
+
+        removed
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{self, handle, render};
+
+    const NESTED: &str = "a [b [c], d], e, f [g]";
+
+
+    #[test]
+    fn remove_nested_node() {
+        let mut bush = test_util::bush(NESTED);
+
+        let d = unsafe { bush.remove_node(handle(&bush, 'd'), EmptyChildren::Keep) };
+        assert_eq!(d.item, 'd');
+        assert!(d.parent_node().is_none());
+        assert_eq!(render(&bush), "a[b[c]]ef[g]");
+        assert_eq!(bush[0].children().unwrap().last_item(), Some(&'b'));
+
+        // The emptied children are kept or collapsed as requested
+        unsafe { bush.remove_node(handle(&bush, 'c'), EmptyChildren::Keep) };
+        assert_eq!(render(&bush), "a[b[]]ef[g]");
+        unsafe { bush.remove_node(handle(&bush, 'g'), EmptyChildren::Collapse) };
+        assert_eq!(render(&bush), "a[b[]]ef");
+        unsafe { bush.remove_node(handle(&bush, 'b'), EmptyChildren::Collapse) };
+        assert_eq!(render(&bush), "aef");
+        assert_eq!(bush.total_node_count(), 3);
+
+        // The top layer has no parent to collapse
+        unsafe { bush.remove_node(handle(&bush, 'a'), EmptyChildren::Collapse) };
+        unsafe { bush.remove_node(handle(&bush, 'e'), EmptyChildren::Collapse) };
+        unsafe { bush.remove_node(handle(&bush, 'f'), EmptyChildren::Collapse) };
+        assert!(bush.is_empty());
+        assert!(bush.first_node().is_none());
+    }
+
+
+    #[test]
+    #[should_panic(expected = "not in the bush")]
+    fn remove_foreign_node() {
+        let mut bush = test_util::bush(NESTED);
+        let other = test_util::bush(NESTED);
+        unsafe { bush.remove_node(handle(&other, 'c'), EmptyChildren::Keep) };
+    }
+
+
+    #[test]
+    fn remove_nested_slice() {
+        let mut bush = test_util::bush(NESTED);
+
+        let slice = unsafe { bush.remove_slice(handle(&bush, 'b'), handle(&bush, 'd'), EmptyChildren::Collapse) };
+        assert_eq!(render(&slice), "b[c]d");
+        assert_eq!(render(&bush), "aef[g]");
+        assert_eq!(bush.total_node_count(), 4);
+
+        let slice = unsafe { bush.remove_slice(handle(&bush, 'e'), handle(&bush, 'f'), EmptyChildren::Keep) };
+        assert_eq!(render(&slice), "ef[g]");
+        assert_eq!(render(&bush), "a");
+        assert_eq!(bush.last_item(), Some(&'a'));
+    }
+
+
+    #[test]
+    fn remove_where() {
+        let mut bush = test_util::bush(NESTED);
+        bush[1].set_children(Some("xc".chars().collect()));
+
+        // `c` is removed at every depth, and the branches of the removed `f` are not tested
+        let removed = bush.remove_where(|node| matches!(node.item, 'c' | 'f'), EmptyChildren::Collapse);
+        assert_eq!(removed.iter().map(|node| node.item).collect::<String>(), "ccf");
+        assert_eq!(render(&bush), "a[bd]e[x]");
+        assert_eq!(bush.total_node_count(), 5);
+
+        let removed = bush.remove_where(|node| node.item != 'a', EmptyChildren::Keep);
+        assert_eq!(removed.iter().map(|node| node.item).collect::<String>(), "bde");
+        assert_eq!(render(&bush), "a[]");
+    }
+
+
+    #[test]
+    fn take_at_path() {
+        let mut bush = test_util::bush(NESTED);
+
+        assert!(bush.take_at_path(&[0, 0, 3].into(), EmptyChildren::Collapse).is_none());
+        assert_eq!(bush.take_at_path(&[0, 0, 0].into(), EmptyChildren::Collapse).map(|node| node.item), Some('c'));
+        assert_eq!(render(&bush), "a[bd]ef[g]");
+        assert_eq!(bush.take_at_path(&[2, 0].into(), EmptyChildren::Keep).map(|node| node.item), Some('g'));
+        assert_eq!(render(&bush), "a[bd]ef[]");
+    }
+
+
+    #[test]
+    fn collapse_nested_bush() {
+        let mut bush = test_util::bush(NESTED);
+
+        // Removing from a children bush collapses the layers below it, but never the children bush itself
+        let children = bush[0].children.as_mut().unwrap();
+        unsafe { children.remove_node(handle(children, 'c'), EmptyChildren::Collapse) };
+        let slice = unsafe { children.remove_slice(handle(children, 'b'), handle(children, 'd'), EmptyChildren::Collapse) };
+        assert_eq!(render(&slice), "bd");
+        assert!(children.is_empty());
+        children.append('x');
+        assert_eq!(children.take_at_path(&[0].into(), EmptyChildren::Collapse).map(|node| node.item), Some('x'));
+        assert_eq!(render(&bush), "a[]ef[g]");
+
+        let children = bush[2].children.as_mut().unwrap();
+        assert_eq!(children.remove_where(|_| true, EmptyChildren::Collapse).len(), 1);
+        assert!(children.is_empty());
+        assert_eq!(render(&bush), "a[]ef[]");
+        assert_eq!(bush.total_node_count(), 3);
+    }
+
+}
@@ -0,0 +1,285 @@
+use std::fmt;
+use std::error::Error;
+
+use super::{link_run, unlink_run, Bush, BushNode, Layer};
+
+
+/// Settings of `Bush::rewrite_windows`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RewriteOptions {
+
+    width: usize,
+    nested: bool,
+    max_passes: usize,
+
+}
+
+
+impl RewriteOptions {
+
+    /// Create options for windows of up to `width` nodes, rewriting the top layer only, for up to 100 passes.
+    /// Panics if `width` is 0.
+    pub fn new(width: usize) -> RewriteOptions {
+        assert!(width > 0, "rewrite windows must have at least one node");
+
+        RewriteOptions {
+            width,
+            nested: false,
+            max_passes: 100,
+        }
+    }
+
+
+    /// Set whether the nested layers are rewritten too
+    pub fn nested(mut self, nested: bool) -> Self {
+        self.nested = nested;
+        self
+    }
+
+
+    /// Set how many passes are made at most while looking for a fixpoint.
+    /// Panics if `max_passes` is 0.
+    pub fn max_passes(mut self, max_passes: usize) -> Self {
+        assert!(max_passes > 0, "rewriting must make at least one pass");
+        self.max_passes = max_passes;
+        self
+    }
+
+}
+
+
+/// Reason why a rewrite did not complete
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RewriteError {
+
+    /// The last allowed pass still rewrote some windows. The rewrites made so far are kept.
+    NoFixpoint { passes: usize, rewrites: usize },
+
+}
+
+
+impl fmt::Display for RewriteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RewriteError::NoFixpoint { passes, rewrites } => write!(f, "no fixpoint was reached after {passes} passes and {rewrites} rewrites"),
+        }
+    }
+}
+
+
+impl Error for RewriteError {}
+
+
+/// Slide once over `layer`, replacing the windows matched by `matches`.
+/// Return the number of rewrites.
+unsafe fn rewrite_layer<T, M, R>(layer: *mut Layer<T>, width: usize, matches: &mut M, replace: &mut R) -> usize
+where
+    M: FnMut(&[&BushNode<T>]) -> Option<usize>,
+    R: FnMut(Bush<T>) -> Bush<T>
+{
+    let mut rewrites = 0;
+    let mut window: Vec<*mut BushNode<T>> = Vec::with_capacity(width);
+    let mut node = (*layer).first;
+
+    while !node.is_null() {
+        window.clear();
+        let mut next = node;
+        while window.len() < width && !next.is_null() {
+            window.push(next);
+            next = (*next).right;
+        }
+
+        let matched = {
+            let nodes: Vec<&BushNode<T>> = window.iter().map(|&node| &*node).collect();
+            matches(&nodes)
+        };
+
+        match matched {
+            Some(len) if len > 0 => {
+                assert!(len <= window.len(), "the matched length (is {len}) should be <= the window length (is {})", window.len());
+
+                let start = node;
+                let end = window[len - 1];
+                let left = (*start).left;
+                let right = (*end).right;
+
+                unlink_run(start, end);
+                let mut replacement = replace(Bush::from_run(start, end));
+                if let Some((first, last)) = replacement.take_run() {
+                    link_run(layer, left, right, first, last);
+                }
+
+                // The replacement is not matched again in the same pass
+                rewrites += 1;
+                node = right;
+            },
+            _ => node = (*node).right
+        }
+    }
+
+    rewrites
+}
+
+
+impl<T> Bush<T> {
+
+    /// Slide a window of sibling nodes over the top layer, and replace the windows matched by `matches`.
+    ///
+    /// `matches` is given the nodes starting at each position, up to the window width, and fewer at the end of the layer.
+    /// It returns how many nodes of the window to replace, or `None` to move on.
+    /// The matched nodes are extracted with their branches into a bush that `replace` turns into the replacement nodes,
+    /// so their children can be kept, moved into a new node or dropped.
+    /// The scan resumes after the replacement, and passes are repeated until one makes no rewrite.
+    ///
+    /// Return the number of rewrites, or an error if the last allowed pass still rewrote something.
+    /// Panics if `matches` returns a length greater than the window.
+    pub fn rewrite_windows<M, R>(&mut self, options: RewriteOptions, mut matches: M, mut replace: R) -> Result<usize, RewriteError>
+    where
+        M: FnMut(&[&BushNode<T>]) -> Option<usize>,
+        R: FnMut(Bush<T>) -> Bush<T>
+    {
+        let mut rewrites = 0;
+
+        for _ in 0..options.max_passes {
+            let mut pass_rewrites = 0;
+            let mut layers: Vec<*mut Layer<T>> = vec![self.layer.as_ptr()];
+
+            while let Some(layer) = layers.pop() {
+                unsafe {
+                    pass_rewrites += rewrite_layer(layer, options.width, &mut matches, &mut replace);
+
+                    if options.nested {
+                        let mut node = (*layer).first;
+                        while !node.is_null() {
+                            if let Some(children) = &(*node).children {
+                                layers.push(children.layer.as_ptr());
+                            }
+                            node = (*node).right;
+                        }
+                    }
+                }
+            }
+
+            if pass_rewrites == 0 {
+                return Ok(rewrites);
+            }
+            rewrites += pass_rewrites;
+        }
+
+        Err(RewriteError::NoFixpoint { passes: options.max_passes, rewrites })
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{self, render_with};
+
+
+    /// Match `ident ( arg )` windows
+    fn call(window: &[&BushNode<String>]) -> Option<usize> {
+        match window {
+            [name, open, _, close, ..] if name.item.starts_with('f') && open.item == "(" && close.item == ")" => Some(4),
+            _ => None
+        }
+    }
+
+
+    /// Replace a call window with a `call` node owning the name and the argument
+    fn wrap_call(mut nodes: Bush<String>) -> Bush<String> {
+        nodes.remove(3);
+        nodes.remove(1);
+        let mut call = test_util::bush("call");
+        call[0].set_children(Some(nodes));
+        call
+    }
+
+
+    #[test]
+    fn rewrite_top_layer() {
+        let mut bush = test_util::bush("x = f ( a ) ; g ( b )");
+
+        assert_eq!(bush.rewrite_windows(RewriteOptions::new(4), call, wrap_call), Ok(1));
+        assert_eq!(render_with(&bush, "[", "]", " "), "x = call[f a] ; g ( b )");
+        assert_eq!(bush.total_node_count(), 11 - 4 + 3);
+        assert_eq!(bush.len(), 8);
+        assert_eq!(bush.last_item().map(String::as_str), Some(")"));
+    }
+
+
+    #[test]
+    fn fixpoint() {
+        // Collapse `x + x` pairs into `x`, which takes several passes
+        let mut bush = test_util::bush("x + x + x + x");
+        let pair = |window: &[&BushNode<String>]| match window {
+            [a, op, b] if a.item == "x" && op.item == "+" && b.item == "x" => Some(3),
+            _ => None
+        };
+
+        let rewrites = bush.rewrite_windows(RewriteOptions::new(3), pair, |_| test_util::bush("x"));
+        assert_eq!(rewrites, Ok(3));
+        assert_eq!(render_with(&bush, "[", "]", " "), "x");
+
+        // A rule that always rewrites never reaches a fixpoint
+        let mut bush: Bush<String> = test_util::bush("a");
+        let rewrites = bush.rewrite_windows(RewriteOptions::new(1).max_passes(5), |_| Some(1), |nodes| nodes);
+        assert_eq!(rewrites, Err(RewriteError::NoFixpoint { passes: 5, rewrites: 5 }));
+        assert_eq!(render_with(&bush, "[", "]", " "), "a");
+
+        // A single pass that makes no rewrite is a fixpoint
+        let rewrites = bush.rewrite_windows(RewriteOptions::new(1).max_passes(1), |_| None, |nodes| nodes);
+        assert_eq!(rewrites, Ok(0));
+    }
+
+
+    #[test]
+    #[should_panic(expected = "at least one pass")]
+    fn zero_passes() {
+        RewriteOptions::new(1).max_passes(0);
+    }
+
+
+    #[test]
+    fn replacements_keep_branches() {
+        let mut bush: Bush<String> = test_util::bush("a b c");
+        bush[1].set_children(Some(test_util::bush("b1 b2")));
+
+        // Replace `b` with a renamed node that keeps its children, and drop `c`
+        let rewrites = bush.rewrite_windows(
+            RewriteOptions::new(1),
+            |window| matches!(window[0].item.as_str(), "b" | "c").then_some(1),
+            |mut nodes| {
+                let mut node = nodes.pop_front().unwrap();
+                if node.item == "c" {
+                    return Bush::new();
+                }
+                let mut renamed = test_util::bush("B");
+                renamed[0].set_children(node.take_children());
+                renamed
+            }
+        );
+
+        assert_eq!(rewrites, Ok(2));
+        assert_eq!(render_with(&bush, "[", "]", " "), "a B[b1 b2]");
+        assert_eq!(bush.total_node_count(), 4);
+        assert!(bush[1].children().unwrap()[0].parent_node().is_some_and(|parent| parent.item == "B"));
+    }
+
+
+    #[test]
+    fn nested_layers() {
+        let mut bush = test_util::bush("f ( a ) block");
+        bush[4].set_children(Some(test_util::bush("y = f ( b )")));
+
+        let mut top_only = bush.clone();
+        assert_eq!(top_only.rewrite_windows(RewriteOptions::new(4), call, wrap_call), Ok(1));
+        assert_eq!(render_with(&top_only, "[", "]", " "), "call[f a] block[y = f ( b )]");
+
+        assert_eq!(bush.rewrite_windows(RewriteOptions::new(4).nested(true), call, wrap_call), Ok(2));
+        assert_eq!(render_with(&bush, "[", "]", " "), "call[f a] block[y = call[f b]]");
+        assert_eq!(bush.total_node_count(), 9);
+    }
+
+}
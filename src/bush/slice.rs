@@ -0,0 +1,226 @@
+use std::fmt;
+use std::mem;
+
+use super::{Bush, BushLayerIter, BushLayerIterMut, BushNode};
+
+
+/// An owned run of sibling nodes, along with their branches, detached from any bush.
+/// Dropping the slice drops all of its nodes.
+pub struct BushSlice<T> {
+
+    /// The nodes of the slice, kept in the top layer of a bush of their own
+    nodes: Bush<T>,
+
+}
+
+
+impl<T> BushSlice<T> {
+
+    /// Create a slice from the nodes of the bush's top layer
+    pub(super) fn from_bush(nodes: Bush<T>) -> BushSlice<T> {
+        BushSlice { nodes }
+    }
+
+
+    /// Return the number of nodes in the slice, not counting their branches
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+
+    /// Return whether the slice has no nodes
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+
+    /// Get the first node of the slice
+    pub fn first(&self) -> Option<&BushNode<T>> {
+        self.nodes.first_node()
+    }
+
+
+    /// Get the last node of the slice
+    pub fn last(&self) -> Option<&BushNode<T>> {
+        self.nodes.last_node()
+    }
+
+
+    /// Get an iterator over the nodes of the slice
+    pub fn iter(&self) -> BushLayerIter<'_, T> {
+        self.nodes.iter_nodes()
+    }
+
+
+    /// Walk the nodes of the slice mutably, one node at a time
+    pub fn iter_mut(&mut self) -> BushLayerIterMut<'_, T> {
+        self.nodes.iter_nodes_mut()
+    }
+
+
+    /// Split the slice before index `mid`, returning the nodes before it and the nodes from it onwards.
+    /// Panics if `mid` is greater than the slice's length.
+    pub fn split_at(mut self, mid: usize) -> (BushSlice<T>, BushSlice<T>) {
+        let len = self.len();
+        assert!(mid <= len, "split index (is {mid}) should be <= len (is {len})");
+
+        let tail = self.nodes.split_off(mid);
+        (self, BushSlice::from_bush(tail))
+    }
+
+
+    /// Move the nodes of the other slice to the end of this one
+    pub fn append(&mut self, other: BushSlice<T>) {
+        self.nodes.append_bush(other.nodes);
+    }
+
+
+    /// Reverse the order of the nodes of the slice. Their branches are left as they are.
+    pub fn reverse(&mut self) {
+        let layer = self.nodes.layer_mut();
+        let mut node = layer.first;
+
+        while !node.is_null() {
+            unsafe {
+                let next = (*node).right;
+                mem::swap(&mut (*node).left, &mut (*node).right);
+                node = next;
+            }
+        }
+        mem::swap(&mut layer.first, &mut layer.last);
+    }
+
+
+    /// Turn the slice into a bush whose top layer is the nodes of the slice
+    pub fn into_bush(self) -> Bush<T> {
+        self.nodes
+    }
+
+}
+
+
+impl<T> fmt::Debug for BushSlice<T>
+where
+    T: fmt::Debug
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::items;
+
+
+    /// Build a slice of the nodes `0..len`, where each node has a child ten times its item
+    fn slice_with_children(len: i32) -> BushSlice<i32> {
+        let mut bush: Bush<i32> = (0..len).collect();
+        let mut walk = bush.iter_nodes_mut();
+        while let Some(node) = walk.next_node() {
+            node.set_children(Some([node.item * 10].into_iter().collect()));
+        }
+        bush.as_slice().unwrap()
+    }
+
+
+    #[test]
+    fn drop_frees_the_whole_run() {
+        // Miri reports the nodes in the middle of the run, or their branches, if they leak
+        drop(slice_with_children(5));
+
+        // A single node slice starts and ends at the same node
+        let mut bush: Bush<i32> = (0..3).collect();
+        let middle = bush[1].into_handle();
+        let slice = unsafe { bush.extract_slice(middle, middle) };
+        assert_eq!(slice.len(), 1);
+        assert_eq!(items(&slice.nodes), vec![1]);
+        drop(slice);
+        assert_eq!(bush.iter_items().copied().collect::<Vec<_>>(), vec![0, 2]);
+    }
+
+
+    #[test]
+    fn access() {
+        let mut slice = slice_with_children(4);
+
+        assert_eq!(slice.len(), 4);
+        assert!(!slice.is_empty());
+        assert_eq!(slice.first().map(|node| node.item), Some(0));
+        assert_eq!(slice.last().and_then(|node| node.children()).and_then(Bush::first_item), Some(&30));
+        assert!(slice.first().unwrap().parent_node().is_none());
+
+        let mut walk = slice.iter_mut();
+        while let Some(node) = walk.next_node() {
+            node.item += 1;
+        }
+        assert_eq!(items(&slice.nodes), vec![1, 2, 3, 4]);
+        assert_eq!(slice.iter().rev().map(|node| node.item).collect::<Vec<_>>(), vec![4, 3, 2, 1]);
+        assert_eq!(format!("{:?}", slice.split_at(1).0), "[BushNode { item: 1, children: Some([BushNode { item: 0, children: None }]) }]");
+    }
+
+
+    #[test]
+    fn split_and_append() {
+        let (mut head, tail) = slice_with_children(5).split_at(2);
+        assert_eq!(items(&head.nodes), vec![0, 1]);
+        assert_eq!(items(&tail.nodes), vec![2, 3, 4]);
+
+        let (empty, tail) = tail.split_at(0);
+        assert!(empty.is_empty());
+        assert!(empty.first().is_none());
+
+        head.append(empty);
+        head.append(tail);
+        assert_eq!(items(&head.nodes), vec![0, 1, 2, 3, 4]);
+        assert_eq!(head.last().map(|node| node.item), Some(4));
+
+        let bush = head.into_bush();
+        assert_eq!(bush.len(), 5);
+        assert_eq!(bush.total_node_count(), 10);
+    }
+
+
+    #[test]
+    #[should_panic(expected = "split index")]
+    fn split_out_of_bounds() {
+        slice_with_children(2).split_at(3);
+    }
+
+
+    #[test]
+    fn reverse() {
+        let mut slice = slice_with_children(4);
+        slice.reverse();
+
+        assert_eq!(items(&slice.nodes), vec![3, 2, 1, 0]);
+        assert_eq!(slice.iter().rev().map(|node| node.item).collect::<Vec<_>>(), vec![0, 1, 2, 3]);
+        assert_eq!(slice.first().map(|node| node.item), Some(3));
+        assert_eq!(slice.first().and_then(|node| node.children()).and_then(Bush::first_item), Some(&30));
+
+        // The reversed run can be inserted back into a bush
+        let mut bush: Bush<i32> = [-1].into_iter().collect();
+        bush[0].insert_slice_right(slice);
+        assert_eq!(bush.iter_items().copied().collect::<Vec<_>>(), vec![-1, 3, 2, 1, 0]);
+        assert_eq!(bush.last_item(), Some(&0));
+        assert_eq!(bush.total_node_count(), 9);
+    }
+
+
+    #[test]
+    fn insert_slices() {
+        let mut bush: Bush<i32> = [100].into_iter().collect();
+
+        bush[0].insert_slice_left(slice_with_children(2));
+        bush[2].insert_slice_right(slice_with_children(1));
+        bush[0].insert_slice_left(slice_with_children(1).split_at(0).0);
+
+        assert_eq!(bush.iter_items().copied().collect::<Vec<_>>(), vec![0, 1, 100, 0]);
+        assert_eq!(bush.first_item(), Some(&0));
+        assert_eq!(bush.total_node_count(), 7);
+        assert!(bush[1].children().unwrap()[0].parent_node().is_some_and(|parent| parent.item == 1));
+    }
+
+}
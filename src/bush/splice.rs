@@ -0,0 +1,271 @@
+use std::ops::RangeBounds;
+
+use super::{link_layer, node_layer, null_node, unlink_run, Bush, BushNode, NodeHandle};
+
+
+// Moving the whole top layer of another bush retires that layer instead of visiting its nodes, so appending,
+// prepending, inserting and splicing in a bush are O(1) in the number of moved nodes, amortized over the lookups
+// that follow the forwarding links. Splitting a layer can't be done that way, since the nodes that leave
+// and the ones that stay point to the same layer: it visits each node that leaves once.
+
+
+impl<T> Bush<T> {
+
+    /// Move the nodes of the other bush's top layer, with their branches, to the end of this bush's top layer
+    pub fn append_bush(&mut self, other: Bush<T>) {
+        unsafe {
+            link_layer(self.layer.as_ptr(), self.layer().last, null_node(), other);
+        }
+    }
+
+
+    /// Move the nodes of the other bush's top layer, with their branches, to the start of this bush's top layer
+    pub fn prepend_bush(&mut self, other: Bush<T>) {
+        unsafe {
+            link_layer(self.layer.as_ptr(), null_node(), self.layer().first, other);
+        }
+    }
+
+
+    /// Split the bush's top layer at index `at`, returning the nodes from `at` onwards with their branches.
+    /// Panics if `at` is greater than the layer's length.
+    pub fn split_off(&mut self, at: usize) -> Bush<T> {
+        let len = self.len();
+        assert!(at <= len, "split index (is {at}) should be <= len (is {len})");
+
+        if at == len {
+            Bush::new()
+        } else {
+            unsafe { self.split_off_run(self.node_ptr(at)) }
+        }
+    }
+
+
+    /// Split the bush's top layer before the given node, returning the node and the ones to its right with their branches.
+    /// Panics if the node is not in the bush's top layer.
+    ///
+    /// # Safety
+    ///
+    /// The handle must refer to a node that has not been removed. A node of another bush must not be mutably borrowed during the call.
+    pub unsafe fn split_off_node(&mut self, node: NodeHandle<T>) -> Bush<T> {
+        let node = node.as_ptr() as *mut BushNode<T>;
+        assert!(node_layer(node) == self.layer.as_ptr(), "the node is not in the bush's top layer");
+
+        self.split_off_run(node)
+    }
+
+
+    /// Detach the nodes of the top layer from `start` to the end into a new bush
+    unsafe fn split_off_run(&mut self, start: *mut BushNode<T>) -> Bush<T> {
+        let end = self.layer().last;
+        unlink_run(start, end);
+        Bush::from_run(start, end)
+    }
+
+
+    /// Replace the nodes in the given index range of the bush's top layer with the top layer of `replacement`,
+    /// returning the replaced nodes with their branches.
+    /// Panics if the range is decreasing or goes past the end of the layer.
+    pub fn splice<R>(&mut self, range: R, replacement: Bush<T>) -> Bush<T>
+    where
+        R: RangeBounds<usize>
+    {
+        let (start, end) = self.layer_range(range, "splice");
+
+        let (left, right, removed) = if start == end {
+            let right = self.node_ptr(start);
+            let left = if right.is_null() { self.layer().last } else { unsafe { (*right).left } };
+            (left, right, Bush::new())
+        } else {
+            let first = self.node_ptr(start);
+            let last = self.node_ptr(end - 1);
+            unsafe {
+                let left = (*first).left;
+                let right = (*last).right;
+                unlink_run(first, last);
+                (left, right, Bush::from_run(first, last))
+            }
+        };
+
+        unsafe {
+            link_layer(self.layer.as_ptr(), left, right, replacement);
+        }
+
+        removed
+    }
+
+
+    /// Move the nodes of the other bush's top layer, with their branches, right after the given node.
+    /// The node can be at any depth of the bush. Panics if the node is not in the bush.
+    ///
+    /// # Safety
+    ///
+    /// The handle must refer to a node that has not been removed. A node of another bush must not be mutably borrowed during the call.
+    pub unsafe fn insert_bush_after(&mut self, node: NodeHandle<T>, other: Bush<T>) {
+        let node = node.as_ptr() as *mut BushNode<T>;
+        assert!(self.owns_node(node), "the node is not in the bush");
+
+        link_layer(node_layer(node), node, (*node).right, other);
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{BushPath, EmptyChildren};
+    use crate::test_util::{self, items, render};
+    use std::ptr;
+
+
+    #[test]
+    fn append_and_prepend() {
+        let mut bush: Bush<i32> = (3..5).collect();
+        let mut tail: Bush<i32> = (5..7).collect();
+        tail[0].set_children(Some([50].into_iter().collect()));
+
+        bush.append_bush(tail);
+        bush.prepend_bush((0..3).collect());
+        bush.append_bush(Bush::new());
+        bush.prepend_bush(Bush::new());
+
+        assert_eq!(items(&bush), (0..7).collect::<Vec<_>>());
+        assert_eq!(bush.first_item(), Some(&0));
+        assert_eq!(bush.last_item(), Some(&6));
+        assert_eq!(bush.total_node_count(), 8);
+        assert_eq!(bush.iter_items().rev().copied().collect::<Vec<_>>(), (0..7).rev().collect::<Vec<_>>());
+
+        let mut empty = Bush::new();
+        empty.append_bush(bush);
+        assert_eq!(empty.len(), 7);
+        assert!(empty[5].children().unwrap()[0].parent_node().is_some_and(|parent| parent.item == 5));
+    }
+
+
+    #[test]
+    fn split_off() {
+        let mut bush: Bush<i32> = (0..6).collect();
+
+        let tail = bush.split_off(4);
+        assert_eq!(items(&bush), vec![0, 1, 2, 3]);
+        assert_eq!(items(&tail), vec![4, 5]);
+        assert_eq!(bush.last_item(), Some(&3));
+
+        let middle = unsafe { bush.split_off_node(bush[2].into_handle()) };
+        assert_eq!(items(&bush), vec![0, 1]);
+        assert_eq!(items(&middle), vec![2, 3]);
+
+        assert!(bush.split_off(2).is_empty());
+        let all = bush.split_off(0);
+        assert!(bush.is_empty());
+        assert_eq!(bush.first_item(), None);
+        assert_eq!(items(&all), vec![0, 1]);
+    }
+
+
+    #[test]
+    #[should_panic(expected = "not in the bush's top layer")]
+    fn split_off_nested_node() {
+        let mut bush: Bush<i32> = (0..2).collect();
+        bush[0].set_children(Some([10].into_iter().collect()));
+
+        let nested = bush[0].children().unwrap()[0].into_handle();
+        unsafe { bush.split_off_node(nested) };
+    }
+
+
+    #[test]
+    fn splice() {
+        let mut bush: Bush<i32> = (0..5).collect();
+
+        let removed = bush.splice(1..3, [10, 11, 12].into_iter().collect());
+        assert_eq!(items(&removed), vec![1, 2]);
+        assert_eq!(items(&bush), vec![0, 10, 11, 12, 3, 4]);
+
+        // An empty range inserts, an empty replacement removes
+        assert!(bush.splice(6..6, [13].into_iter().collect()).is_empty());
+        assert!(bush.splice(0..0, [-1].into_iter().collect()).is_empty());
+        assert_eq!(items(&bush.splice(..2, Bush::new())), vec![-1, 0]);
+        assert_eq!(items(&bush), vec![10, 11, 12, 3, 4, 13]);
+
+        let removed = bush.splice(.., (0..2).collect());
+        assert_eq!(removed.len(), 6);
+        assert_eq!(items(&bush), vec![0, 1]);
+        assert_eq!(bush.first_item(), Some(&0));
+        assert_eq!(bush.last_item(), Some(&1));
+        assert_eq!(bush.total_node_count(), 2);
+    }
+
+
+    #[test]
+    #[should_panic(expected = "splice range end")]
+    fn splice_out_of_bounds() {
+        let mut bush: Bush<i32> = (0..3).collect();
+        bush.splice(2..4, Bush::new());
+    }
+
+
+    #[test]
+    fn insert_bush_after() {
+        let mut bush: Bush<i32> = (0..2).collect();
+        bush[1].set_children(Some([10, 13].into_iter().collect()));
+
+        let last = bush[1].into_handle();
+        unsafe { bush.insert_bush_after(last, (2..4).collect()) };
+        assert_eq!(items(&bush), vec![0, 1, 2, 3]);
+        assert_eq!(bush.last_item(), Some(&3));
+
+        // Nested layers keep their own ends consistent
+        let nested = bush[1].children().unwrap()[0].into_handle();
+        unsafe { bush.insert_bush_after(nested, (11..13).collect()) };
+        let children = bush[1].children().unwrap();
+        assert_eq!(items(children), vec![10, 11, 12, 13]);
+        assert_eq!(children.last_item(), Some(&13));
+        assert_eq!(children[2].parent_node().map(|node| node.item), Some(1));
+        assert_eq!(bush.total_node_count(), 8);
+    }
+
+
+    #[test]
+    fn forwarded_nodes() {
+        let mut bush: Bush<i32> = test_util::bush("0, 1 [10]");
+        let mut middle: Bush<i32> = test_util::bush("2 [20, 21], 3");
+        middle.append_bush(test_util::bush("4 [40]"));
+        bush.append_bush(middle);
+        bush.prepend_bush(test_util::bush("-1"));
+
+        // The merged nodes still point to their retired layers, which forward to the live one
+        let node = unsafe { &*test_util::handle(&bush, 4).as_ptr() };
+        assert!(!ptr::eq(node.linked_layer, bush.layer.as_ptr()));
+        assert!(unsafe { ptr::eq(node_layer(node), bush.layer.as_ptr()) });
+
+        assert_eq!(render(&bush), "-101[10]2[2021]34[40]");
+        assert_eq!(bush.total_node_count(), 10);
+        assert_eq!(bush[5].children().unwrap()[0].parent_node().map(|node| node.item), Some(4));
+
+        let forwarded = test_util::handle(&bush, 3);
+        assert!(bush.checked_handle(unsafe { bush.node(forwarded) }).is_ok());
+        assert_eq!(unsafe { bush.path_of(test_util::handle(&bush, 21)) }, Some(BushPath::from([3, 1])));
+        assert_eq!(unsafe { bush.extract_node(forwarded) }.item, 3);
+
+        // Merging the merged bush again chains the forwarding, and splitting re-homes the leaving nodes
+        let mut outer: Bush<i32> = test_util::bush("5 [50]");
+        unsafe { outer.insert_bush_after(test_util::handle(&outer, 50), bush) };
+        let children = outer[0].children().unwrap();
+        assert_eq!(items(children), vec![50, -1, 0, 1, 2, 4]);
+        assert_eq!(children[5].parent_node().map(|node| node.item), Some(5));
+        assert_eq!(outer.total_node_count(), 11);
+
+        let removed = unsafe { outer.remove_node(test_util::handle(&outer, 2), EmptyChildren::Keep) };
+        assert_eq!(removed.children().map(items), Some(vec![20, 21]));
+
+        let mut children = outer[0].take_children().unwrap();
+        let tail = children.split_off(3);
+        assert_eq!(items(&tail), vec![1, 4]);
+        assert!(tail[1].children().unwrap()[0].parent_node().is_some_and(|parent| parent.item == 4));
+        assert!(unsafe { tail.owns_node(&tail[1]) && !children.owns_node(&tail[1]) });
+        assert_eq!(children.total_node_count(), 3);
+    }
+
+}
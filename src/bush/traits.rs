@@ -0,0 +1,675 @@
+use std::cmp::Ordering;
+use std::fmt::{self, Write};
+use std::hash::{Hash, Hasher};
+use std::iter::FusedIterator;
+
+use super::{Bush, BushLayerItemIterMut, BushLayerIter, BushNode, IterItems};
+
+
+impl<T> FromIterator<T> for Bush<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut bush = Bush::new();
+        bush.extend(iter);
+        bush
+    }
+}
+
+
+impl<T> Extend<T> for Bush<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.append(item);
+        }
+    }
+}
+
+
+/// An owning iterator over the items of a bush's top layer.
+/// The branches of each node are dropped along with it.
+pub struct IntoIter<T> {
+
+    bush: Bush<T>,
+
+}
+
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.bush.pop_front().map(|node| node.item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.bush.len(), Some(self.bush.len()))
+    }
+}
+
+
+impl<T> DoubleEndedIterator for IntoIter<T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.bush.pop_back().map(|node| node.item)
+    }
+}
+
+
+impl<T> ExactSizeIterator for IntoIter<T> {}
+
+
+impl<T> FusedIterator for IntoIter<T> {}
+
+
+impl<T> IntoIterator for Bush<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter { bush: self }
+    }
+}
+
+
+impl<'a, T> IntoIterator for &'a Bush<T> {
+    type Item = &'a T;
+    type IntoIter = IterItems<T, BushLayerIter<'a, T>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_items()
+    }
+}
+
+
+impl<'a, T> IntoIterator for &'a mut Bush<T> {
+    type Item = &'a mut T;
+    type IntoIter = BushLayerItemIterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_items_mut()
+    }
+}
+
+
+impl<T> ToOwned for BushNode<T>
+where
+    T: Clone
+{
+    type Owned = Box<BushNode<T>>;
+
+    /// Copy the item and the branches into a new detached node
+    fn to_owned(&self) -> Self::Owned {
+        let mut node = BushNode::new(self.item.clone());
+        node.set_children(self.children.clone());
+        node
+    }
+}
+
+
+impl<T> Clone for Box<BushNode<T>>
+where
+    T: Clone
+{
+    fn clone(&self) -> Self {
+        (**self).to_owned()
+    }
+}
+
+
+// Debug output matches the formatter's nesting helpers, but it's written from the structural steps of the bush,
+// so that deep bushes can't overflow the call stack
+
+impl<T> fmt::Debug for Bush<T>
+where
+    T: fmt::Debug
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return f.write_str("[]");
+        }
+
+        f.write_str(if f.alternate() { "[\n" } else { "[" })?;
+        debug_steps(f, Steps::of_bush(self), 1, true)?;
+        f.write_str("]")
+    }
+}
+
+
+impl<T> fmt::Debug for BushNode<T>
+where
+    T: fmt::Debug
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        debug_steps(f, Steps::of_node(self), 0, false)
+    }
+}
+
+
+/// Write the nodes of the steps as `BushNode { item, children }` structs.
+/// `depth` is the indentation of the first layer's nodes in the alternate form, and `entries` tells whether
+/// they are entries of a list, which are separated by commas.
+fn debug_steps<T>(f: &mut fmt::Formatter<'_>, steps: Steps<'_, T>, mut depth: usize, entries: bool) -> fmt::Result
+where
+    T: fmt::Debug
+{
+    let pretty = f.alternate();
+    let base = depth;
+    let mut first = true;
+    let mut steps = steps.peekable();
+
+    while let Some(step) = steps.next() {
+        let node_end = match step {
+            Step::Node(item) => {
+                if pretty {
+                    indent(f, depth)?;
+                    f.write_str("BushNode {\n")?;
+                    indent(f, depth + 1)?;
+                    f.write_str("item: ")?;
+                    write!(Indented { f, depth: depth + 1, on_newline: false }, "{item:#?}")?;
+                    f.write_str(",\n")?;
+                    indent(f, depth + 1)?;
+                    f.write_str("children: ")?;
+                } else {
+                    f.write_str(if first { "BushNode { item: " } else { ", BushNode { item: " })?;
+                    item.fmt(f)?;
+                    f.write_str(", children: ")?;
+                }
+                first = false;
+                false
+            },
+            Step::Leaf => {
+                f.write_str(if pretty { "None,\n" } else { "None" })?;
+                true
+            },
+            Step::Open if matches!(steps.peek(), Some(Step::Close)) => {
+                steps.next();
+                if pretty {
+                    f.write_str("Some(\n")?;
+                    indent(f, depth + 2)?;
+                    f.write_str("[],\n")?;
+                    indent(f, depth + 1)?;
+                    f.write_str("),\n")?;
+                } else {
+                    f.write_str("Some([])")?;
+                }
+                true
+            },
+            Step::Open => {
+                if pretty {
+                    f.write_str("Some(\n")?;
+                    indent(f, depth + 2)?;
+                    f.write_str("[\n")?;
+                } else {
+                    f.write_str("Some([")?;
+                }
+                depth += 3;
+                first = true;
+                false
+            },
+            Step::Close => {
+                depth -= 3;
+                if pretty {
+                    indent(f, depth + 2)?;
+                    f.write_str("],\n")?;
+                    indent(f, depth + 1)?;
+                    f.write_str("),\n")?;
+                } else {
+                    f.write_str("])")?;
+                }
+                true
+            },
+        };
+
+        if node_end {
+            if pretty {
+                indent(f, depth)?;
+                f.write_str(if entries || depth > base { "},\n" } else { "}" })?;
+            } else {
+                f.write_str(" }")?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+
+fn indent(f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+    write!(f, "{:1$}", "", depth * 4)
+}
+
+
+/// Writer that indents the lines after the first one, for items spanning several lines
+struct Indented<'a, 'b> {
+
+    f: &'a mut fmt::Formatter<'b>,
+    depth: usize,
+    on_newline: bool,
+
+}
+
+
+impl fmt::Write for Indented<'_, '_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for line in s.split_inclusive('\n') {
+            if self.on_newline {
+                indent(self.f, self.depth)?;
+            }
+            self.f.write_str(line)?;
+            self.on_newline = line.ends_with('\n');
+        }
+        Ok(())
+    }
+}
+
+
+/// A step of a structural walk over a bush, in depth first order.
+///
+/// Comparing the steps of two bushes orders them like nested vectors of `(item, children)` pairs:
+/// a shorter layer comes before a longer one with the same prefix, and a node without children comes
+/// before an equal node with children. The variant order is what makes this work.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum Step<'a, T> {
+
+    /// The end of a children layer
+    Close,
+
+    /// The item of the next node of the layer
+    Node(&'a T),
+
+    /// The last node has no children
+    Leaf,
+
+    /// The start of the children layer of the last node
+    Open,
+
+}
+
+
+/// Iterator over the structural steps of a bush or of a node's subtree.
+/// The layers being walked are kept on an explicit stack, so deep bushes can't overflow the call stack.
+struct Steps<'a, T> {
+
+    /// The next node of each layer being walked, the innermost last
+    layers: Vec<Option<&'a BushNode<T>>>,
+
+    /// The node whose item was just yielded, and whose children are to be walked next
+    last: Option<&'a BushNode<T>>,
+
+    /// The node walked alone, without its siblings, whose item is yielded first
+    root: Option<&'a BushNode<T>>,
+
+}
+
+
+impl<'a, T> Steps<'a, T> {
+
+    fn of_bush(bush: &'a Bush<T>) -> Self {
+        Self {
+            layers: vec![bush.first_node()],
+            last: None,
+            root: None,
+        }
+    }
+
+
+    fn of_node(node: &'a BushNode<T>) -> Self {
+        Self {
+            layers: vec![None],
+            last: None,
+            root: Some(node),
+        }
+    }
+
+}
+
+
+impl<'a, T> Iterator for Steps<'a, T> {
+    type Item = Step<'a, T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(node) = self.root.take() {
+            self.last = Some(node);
+            return Some(Step::Node(&node.item));
+        }
+
+        if let Some(node) = self.last.take() {
+            return Some(match &node.children {
+                Some(children) => {
+                    self.layers.push(children.first_node());
+                    Step::Open
+                },
+                None => Step::Leaf
+            });
+        }
+
+        let next = self.layers.last_mut()?;
+        match next.take() {
+            Some(node) => {
+                *next = node.right_node();
+                self.last = Some(node);
+                Some(Step::Node(&node.item))
+            },
+            None => {
+                self.layers.pop();
+                if self.layers.is_empty() {
+                    None
+                } else {
+                    Some(Step::Close)
+                }
+            }
+        }
+    }
+}
+
+
+impl<T> PartialEq for Bush<T>
+where
+    T: PartialEq
+{
+    fn eq(&self, other: &Self) -> bool {
+        Steps::of_bush(self).eq(Steps::of_bush(other))
+    }
+}
+
+
+impl<T> Eq for Bush<T>
+where
+    T: Eq
+{}
+
+
+impl<T> PartialOrd for Bush<T>
+where
+    T: PartialOrd
+{
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Steps::of_bush(self).partial_cmp(Steps::of_bush(other))
+    }
+}
+
+
+impl<T> Ord for Bush<T>
+where
+    T: Ord
+{
+    fn cmp(&self, other: &Self) -> Ordering {
+        Steps::of_bush(self).cmp(Steps::of_bush(other))
+    }
+}
+
+
+impl<T> Hash for Bush<T>
+where
+    T: Hash
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // The top layer has no closing step, so its length keeps the hash prefix-free
+        state.write_usize(self.len());
+        for step in Steps::of_bush(self) {
+            step.hash(state);
+        }
+    }
+}
+
+
+impl<T> PartialEq for BushNode<T>
+where
+    T: PartialEq
+{
+    /// Compare the items and the branches of the nodes, ignoring their siblings
+    fn eq(&self, other: &Self) -> bool {
+        Steps::of_node(self).eq(Steps::of_node(other))
+    }
+}
+
+
+impl<T> Eq for BushNode<T>
+where
+    T: Eq
+{}
+
+
+impl<T> PartialOrd for BushNode<T>
+where
+    T: PartialOrd
+{
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Steps::of_node(self).partial_cmp(Steps::of_node(other))
+    }
+}
+
+
+impl<T> Ord for BushNode<T>
+where
+    T: Ord
+{
+    fn cmp(&self, other: &Self) -> Ordering {
+        Steps::of_node(self).cmp(Steps::of_node(other))
+    }
+}
+
+
+impl<T> Hash for BushNode<T>
+where
+    T: Hash
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for step in Steps::of_node(self) {
+            step.hash(state);
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::hash::DefaultHasher;
+
+    use super::super::BushPath;
+    use super::*;
+    use crate::test_util;
+
+    const NESTED: &str = "0 [10, 11], 1";
+
+
+    #[test]
+    fn collect_and_extend() {
+        let mut bush: Bush<i32> = (0..3).collect();
+        bush.extend(3..5);
+
+        assert_eq!(bush.len(), 5);
+        assert!(bush.iter_items().copied().eq(0..5));
+    }
+
+
+    #[test]
+    fn into_iter() {
+        let mut bush = test_util::bush(NESTED);
+
+        for item in &mut bush {
+            *item += 1;
+        }
+        assert_eq!((&bush).into_iter().copied().collect::<Vec<_>>(), vec![1, 2]);
+
+        let mut items = bush.into_iter();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items.next_back(), Some(2));
+        assert_eq!(items.next(), Some(1));
+        assert_eq!(items.next(), None);
+    }
+
+
+    #[test]
+    fn debug() {
+        let bush: Bush<i32> = test_util::bush(NESTED);
+        assert_eq!(
+            format!("{bush:?}"),
+            "[BushNode { item: 0, children: Some([BushNode { item: 10, children: None }, BushNode { item: 11, children: None }]) }, \
+            BushNode { item: 1, children: None }]"
+        );
+        assert_eq!(format!("{:?}", Bush::<i32>::new()), "[]");
+    }
+
+
+    #[test]
+    fn debug_matches_nesting_helpers() {
+        // The same shape written by derived Debug, which uses the formatter's nesting helpers
+        #[derive(Debug)]
+        #[allow(dead_code)]
+        struct BushNode {
+            item: (i32, char),
+            children: Option<Vec<BushNode>>,
+        }
+
+        fn mirror(bush: &Bush<(i32, char)>) -> Vec<BushNode> {
+            bush.iter_nodes().map(|node| BushNode { item: node.item, children: node.children().map(mirror) }).collect()
+        }
+
+        let mut bush = Bush::new();
+        bush.append((0, 'a'));
+        bush.append((3, 'd'));
+        bush.append((4, 'e'));
+        bush[0].append_child((1, 'b'));
+        bush[0].append_child((2, 'c'));
+        bush[1].set_children(Some(Bush::new()));
+        bush[&BushPath::from([0, 1])].append_child((5, 'f'));
+
+        let nodes = mirror(&bush);
+        assert_eq!(format!("{bush:?}"), format!("{nodes:?}"));
+        assert_eq!(format!("{bush:#?}"), format!("{nodes:#?}"));
+        assert_eq!(format!("{:?}", &bush[1]), format!("{:?}", nodes[1]));
+        assert_eq!(format!("{:#?}", &bush[0]), format!("{:#?}", nodes[0]));
+        assert_eq!(format!("{:#?}", Bush::<i32>::new()), "[]");
+    }
+
+
+    #[test]
+    fn deep_debug() {
+        const DEPTH: usize = if cfg!(miri) { 1_000 } else { 100_000 };
+
+        let bush = test_util::chain(DEPTH);
+        let debug = format!("{bush:?}");
+        assert!(debug.starts_with("[BushNode { item: 0, children: Some([BushNode { item: 1, children: "));
+        assert!(debug.ends_with(&format!("item: {}, children: None }}{}]", DEPTH - 1, "]) }".repeat(DEPTH - 1))));
+    }
+
+
+    #[test]
+    fn structural_eq() {
+        let bush = test_util::bush(NESTED);
+        assert_eq!(bush, test_util::bush(NESTED));
+        assert_eq!(bush, bush.clone());
+
+        let mut other = test_util::bush(NESTED);
+        other[&BushPath::from([0, 1])].item = 12;
+        assert_ne!(bush, other);
+
+        // Same items in every layer, but a different shape
+        let mut other = test_util::bush(NESTED);
+        let children = other[0].take_children();
+        other[1].set_children(children);
+        assert_ne!(bush, other);
+
+        // No children and an empty children layer are different
+        let mut other = test_util::bush(NESTED);
+        other[1].set_children(Some(Bush::new()));
+        assert_ne!(bush, other);
+
+        assert_ne!(bush, (0..2).collect());
+        assert_ne!(bush, (0..3).collect());
+    }
+
+
+    fn hash_of<H: Hash + ?Sized>(value: &H) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+
+    #[test]
+    fn node_eq_ignores_siblings() {
+        let bush = test_util::bush(NESTED);
+        let other: Bush<i32> = [5, 1, 6].into_iter().collect();
+
+        assert_eq!(bush[1], other[1]);
+        assert_eq!(bush[1].cmp(&other[1]), Ordering::Equal);
+        assert_eq!(hash_of(&bush[1]), hash_of(&other[1]));
+        assert_ne!(bush[0], other[1]);
+        assert_eq!(bush[0], *bush[0].to_owned());
+    }
+
+
+    #[test]
+    fn node_eq_compares_own_item() {
+        assert_ne!(*BushNode::new(1), *BushNode::new(2));
+        assert_eq!(BushNode::new(1).cmp(&BushNode::new(2)), Ordering::Less);
+        assert_ne!(hash_of(&*BushNode::new(1)), hash_of(&*BushNode::new(2)));
+
+        // Same branches under a different item
+        let bush = test_util::bush(NESTED);
+        let mut other = test_util::bush(NESTED);
+        other[0].item = 2;
+        assert_ne!(bush[0], other[0]);
+        assert_eq!(bush[0].partial_cmp(&other[0]), Some(Ordering::Less));
+    }
+
+
+    #[test]
+    fn node_clone() {
+        let bush = test_util::bush(NESTED);
+        let node = bush[0].to_owned();
+        assert_eq!(node.clone().subtree_size(), 3);
+
+        assert!(node.left_node().is_none() && node.right_node().is_none());
+        assert_eq!(node.subtree_size(), 3);
+
+        let mut other = Bush::new();
+        other.append_node(node);
+        assert_eq!(other[0].children().unwrap()[1].parent_item(), Some(&0));
+        assert_eq!(bush.total_node_count(), 4);
+    }
+
+
+    #[test]
+    fn ordering() {
+        let bush = test_util::bush(NESTED);
+
+        // Nested vectors of (item, children) pairs order the same way
+        let a: Bush<i32> = (0..2).collect();
+        let b: Bush<i32> = (0..1).collect();
+        let c: Bush<i32> = [0, 2].into_iter().collect();
+        let mut d = test_util::bush(NESTED);
+        d[0].append_child(12);
+
+        let mut sorted = vec![bush.clone(), a.clone(), b.clone(), c.clone(), d.clone()];
+        sorted.sort();
+        assert_eq!(sorted, vec![b, a, c, bush, d]);
+    }
+
+
+    #[test]
+    fn hash() {
+        let mut set = HashSet::new();
+        set.insert(test_util::bush(NESTED));
+        set.insert(test_util::bush(NESTED));
+        set.insert((0..2).collect());
+
+        assert_eq!(set.len(), 2);
+        assert!(set.contains(&test_util::bush(NESTED)));
+    }
+
+
+    #[test]
+    fn deep_eq() {
+        const DEPTH: usize = if cfg!(miri) { 1_000 } else { 100_000 };
+
+        let bush = test_util::chain(DEPTH);
+
+        let clone = bush.clone();
+        assert_eq!(bush, clone);
+        assert_eq!(bush.cmp(&clone), Ordering::Equal);
+        assert!(HashSet::from([clone]).contains(&bush));
+    }
+
+}
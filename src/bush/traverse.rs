@@ -0,0 +1,537 @@
+use std::collections::VecDeque;
+use std::iter::Map;
+
+use super::{Bush, BushNode, BushPath, IterItems};
+
+
+pub type IterNodes<'a, T, It> = Map<It, fn((usize, &'a BushNode<T>)) -> &'a BushNode<T>>;
+
+
+/// Position information attached to each node yielded by an annotated traversal.
+/// Implemented for `usize`, the depth of the node, and for `BushPath`, the path of the node.
+///
+/// Both are relative to the layer the traversal starts from, so the nodes of that layer have
+/// depth 0 and single-index paths.
+pub trait Annotation: Clone {
+
+    /// The annotation of the first node of the walked layer
+    fn first() -> Self;
+
+    /// The annotation of the first child of the annotated node
+    fn first_child(&self) -> Self;
+
+    /// The annotation of the right sibling of the annotated node
+    fn next_sibling(&self) -> Self;
+
+}
+
+
+impl Annotation for usize {
+
+    fn first() -> Self {
+        0
+    }
+
+    fn first_child(&self) -> Self {
+        self + 1
+    }
+
+    fn next_sibling(&self) -> Self {
+        *self
+    }
+
+}
+
+
+impl Annotation for BushPath {
+
+    fn first() -> Self {
+        BushPath::from([0])
+    }
+
+    fn first_child(&self) -> Self {
+        let mut path = self.clone();
+        path.push(0);
+        path
+    }
+
+    fn next_sibling(&self) -> Self {
+        let mut path = self.clone();
+        if let Some(i) = path.pop() {
+            path.push(i + 1);
+        }
+        path
+    }
+
+}
+
+
+/// Depth first, pre-order iterator yielding each node with its annotation
+pub struct AnnotatedDFSIter<'a, T, A> {
+
+    nodes: Vec<(A, &'a BushNode<T>)>,
+
+}
+
+
+impl<'a, T, A: Annotation> AnnotatedDFSIter<'a, T, A> {
+
+    fn new(first: Option<&'a BushNode<T>>) -> Self {
+        Self {
+            nodes: first.map(|node| (A::first(), node)).into_iter().collect()
+        }
+    }
+
+}
+
+
+impl<'a, T, A: Annotation> Iterator for AnnotatedDFSIter<'a, T, A> {
+    type Item = (A, &'a BushNode<T>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (annotation, node) = self.nodes.pop()?;
+
+        // Push the right sibling first, so that the children are walked before it
+        if let Some(right) = node.right_node() {
+            self.nodes.push((annotation.next_sibling(), right));
+        }
+        if let Some(first) = node.children().and_then(Bush::first_node) {
+            self.nodes.push((annotation.first_child(), first));
+        }
+
+        Some((annotation, node))
+    }
+}
+
+
+/// Breadth first iterator yielding each node with its annotation
+pub struct AnnotatedBFSIter<'a, T, A> {
+
+    nodes: VecDeque<(A, &'a BushNode<T>)>,
+
+}
+
+
+impl<'a, T, A: Annotation> AnnotatedBFSIter<'a, T, A> {
+
+    fn new(first: Option<&'a BushNode<T>>) -> Self {
+        Self {
+            nodes: first.map(|node| (A::first(), node)).into_iter().collect()
+        }
+    }
+
+}
+
+
+impl<'a, T, A: Annotation> Iterator for AnnotatedBFSIter<'a, T, A> {
+    type Item = (A, &'a BushNode<T>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (annotation, node) = self.nodes.pop_front()?;
+
+        // Same-layer nodes go on the front and lower-layer nodes on the back, like in `BFSIter`
+        if let Some(right) = node.right_node() {
+            self.nodes.push_front((annotation.next_sibling(), right));
+        }
+        if let Some(first) = node.children().and_then(Bush::first_node) {
+            self.nodes.push_back((annotation.first_child(), first));
+        }
+
+        Some((annotation, node))
+    }
+}
+
+
+/// Depth first, post-order iterator yielding each node with its annotation after all of its descendants
+pub struct AnnotatedPostOrderIter<'a, T, A> {
+
+    /// The chain of nodes from the walked layer down to the next node to yield
+    ancestors: Vec<(A, &'a BushNode<T>)>,
+
+    /// The next sibling to descend into before yielding the ancestors
+    next: Option<(A, &'a BushNode<T>)>,
+
+}
+
+
+impl<'a, T, A: Annotation> AnnotatedPostOrderIter<'a, T, A> {
+
+    fn new(first: Option<&'a BushNode<T>>) -> Self {
+        Self {
+            ancestors: Vec::new(),
+            next: first.map(|node| (A::first(), node)),
+        }
+    }
+
+}
+
+
+impl<'a, T, A: Annotation> Iterator for AnnotatedPostOrderIter<'a, T, A> {
+    type Item = (A, &'a BushNode<T>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Descend along the first children down to a node without children
+        if let Some((mut annotation, mut node)) = self.next.take() {
+            while let Some(first) = node.children().and_then(Bush::first_node) {
+                let child_annotation = annotation.first_child();
+                self.ancestors.push((annotation, node));
+                annotation = child_annotation;
+                node = first;
+            }
+            self.ancestors.push((annotation, node));
+        }
+
+        let (annotation, node) = self.ancestors.pop()?;
+        if let Some(right) = node.right_node() {
+            self.next = Some((annotation.next_sibling(), right));
+        }
+
+        Some((annotation, node))
+    }
+}
+
+
+/// Iterator over the nodes without children, or with an empty children layer, in depth first order
+pub struct AnnotatedLeafIter<'a, T, A> {
+
+    nodes: AnnotatedDFSIter<'a, T, A>,
+
+}
+
+
+impl<'a, T, A: Annotation> Iterator for AnnotatedLeafIter<'a, T, A> {
+    type Item = (A, &'a BushNode<T>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.nodes.find(|(_, node)| node.children().is_none_or(Bush::is_empty))
+    }
+}
+
+
+/// Iterator over the levels of a bush, yielding the nodes at each depth in document order
+pub struct Levels<'a, T> {
+
+    level: Vec<&'a BushNode<T>>,
+
+}
+
+
+impl<'a, T> Iterator for Levels<'a, T> {
+    type Item = Vec<&'a BushNode<T>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.level.is_empty() {
+            return None;
+        }
+
+        let next_level = self.level.iter()
+            .filter_map(|node| node.children())
+            .flat_map(Bush::iter_nodes)
+            .collect();
+
+        Some(std::mem::replace(&mut self.level, next_level))
+    }
+}
+
+
+fn strip_depth<T>((_, node): (usize, &BushNode<T>)) -> &BushNode<T> {
+    node
+}
+
+
+impl<T> Bush<T> {
+
+    /// Get an iterator over the levels of the bush, yielding the nodes of each depth from the top layer down.
+    /// The nodes of a level are in document order, and the iteration ends at the first empty level.
+    pub fn levels(&self) -> Levels<'_, T> {
+        Levels {
+            level: self.iter_nodes().collect()
+        }
+    }
+
+
+    /// Get a post-order iterator over the bush, yielding each node after its descendants
+    pub fn post_order_nodes(&self) -> IterNodes<'_, T, AnnotatedPostOrderIter<'_, T, usize>> {
+        self.post_order_nodes_with_depth().map(strip_depth)
+    }
+
+
+    /// Get a post-order iterator over the bush, yielding each item after the items of its descendants
+    pub fn post_order_items(&self) -> IterItems<T, IterNodes<'_, T, AnnotatedPostOrderIter<'_, T, usize>>> {
+        self.post_order_nodes().map(|node| &node.item)
+    }
+
+
+    /// Get an iterator over the nodes of the bush that have no children or an empty children layer, in depth first order
+    pub fn leaf_nodes(&self) -> IterNodes<'_, T, AnnotatedLeafIter<'_, T, usize>> {
+        self.leaf_nodes_with_depth().map(strip_depth)
+    }
+
+
+    /// Get an iterator over the items of the nodes of the bush that have no children or an empty children layer, in depth first order
+    pub fn leaf_items(&self) -> IterItems<T, IterNodes<'_, T, AnnotatedLeafIter<'_, T, usize>>> {
+        self.leaf_nodes().map(|node| &node.item)
+    }
+
+
+    /// Get a depth first search iterator over the bush, yielding each node with its depth
+    pub fn dfs_nodes_with_depth(&self) -> AnnotatedDFSIter<'_, T, usize> {
+        AnnotatedDFSIter::new(self.first_node())
+    }
+
+
+    /// Get a breadth first search iterator over the bush, yielding each node with its depth
+    pub fn bfs_nodes_with_depth(&self) -> AnnotatedBFSIter<'_, T, usize> {
+        AnnotatedBFSIter::new(self.first_node())
+    }
+
+
+    /// Get a post-order iterator over the bush, yielding each node with its depth
+    pub fn post_order_nodes_with_depth(&self) -> AnnotatedPostOrderIter<'_, T, usize> {
+        AnnotatedPostOrderIter::new(self.first_node())
+    }
+
+
+    /// Get an iterator over the nodes of the bush that have no children or an empty children layer, yielding each with its depth
+    pub fn leaf_nodes_with_depth(&self) -> AnnotatedLeafIter<'_, T, usize> {
+        AnnotatedLeafIter { nodes: AnnotatedDFSIter::new(self.first_node()) }
+    }
+
+
+    /// Get a depth first search iterator over the bush, yielding each node with its path
+    pub fn dfs_nodes_with_path(&self) -> AnnotatedDFSIter<'_, T, BushPath> {
+        AnnotatedDFSIter::new(self.first_node())
+    }
+
+
+    /// Get a breadth first search iterator over the bush, yielding each node with its path
+    pub fn bfs_nodes_with_path(&self) -> AnnotatedBFSIter<'_, T, BushPath> {
+        AnnotatedBFSIter::new(self.first_node())
+    }
+
+
+    /// Get a post-order iterator over the bush, yielding each node with its path
+    pub fn post_order_nodes_with_path(&self) -> AnnotatedPostOrderIter<'_, T, BushPath> {
+        AnnotatedPostOrderIter::new(self.first_node())
+    }
+
+
+    /// Get an iterator over the nodes of the bush that have no children or an empty children layer, yielding each with its path
+    pub fn leaf_nodes_with_path(&self) -> AnnotatedLeafIter<'_, T, BushPath> {
+        AnnotatedLeafIter { nodes: AnnotatedDFSIter::new(self.first_node()) }
+    }
+
+}
+
+
+// Like `BushNode::dfs_nodes`, the node traversals walk the descendants of the node,
+// with depths and paths relative to its children layer
+
+impl<T> BushNode<T> {
+
+    fn first_child(&self) -> Option<&BushNode<T>> {
+        self.children().and_then(Bush::first_node)
+    }
+
+
+    /// Get the descendants of the node that are `n` levels below it, in document order.
+    /// The children are one level below, and level 0 is the node itself.
+    pub fn level_below(&self, n: usize) -> Vec<&BushNode<T>> {
+        Levels { level: vec![self] }.nth(n).unwrap_or_default()
+    }
+
+
+    /// Get a post-order iterator over the descendants of the node
+    pub fn post_order_nodes(&self) -> IterNodes<'_, T, AnnotatedPostOrderIter<'_, T, usize>> {
+        self.post_order_nodes_with_depth().map(strip_depth)
+    }
+
+
+    /// Get a post-order iterator over the items of the descendants of the node
+    pub fn post_order_items(&self) -> IterItems<T, IterNodes<'_, T, AnnotatedPostOrderIter<'_, T, usize>>> {
+        self.post_order_nodes().map(|node| &node.item)
+    }
+
+
+    /// Get an iterator over the descendants of the node that have no children or an empty children layer
+    pub fn leaf_nodes(&self) -> IterNodes<'_, T, AnnotatedLeafIter<'_, T, usize>> {
+        self.leaf_nodes_with_depth().map(strip_depth)
+    }
+
+
+    /// Get an iterator over the items of the descendants of the node that have no children or an empty children layer
+    pub fn leaf_items(&self) -> IterItems<T, IterNodes<'_, T, AnnotatedLeafIter<'_, T, usize>>> {
+        self.leaf_nodes().map(|node| &node.item)
+    }
+
+
+    /// Get a depth first search iterator over the descendants of the node, yielding each with its depth below the children
+    pub fn dfs_nodes_with_depth(&self) -> AnnotatedDFSIter<'_, T, usize> {
+        AnnotatedDFSIter::new(self.first_child())
+    }
+
+
+    /// Get a breadth first search iterator over the descendants of the node, yielding each with its depth below the children
+    pub fn bfs_nodes_with_depth(&self) -> AnnotatedBFSIter<'_, T, usize> {
+        AnnotatedBFSIter::new(self.first_child())
+    }
+
+
+    /// Get a post-order iterator over the descendants of the node, yielding each with its depth below the children
+    pub fn post_order_nodes_with_depth(&self) -> AnnotatedPostOrderIter<'_, T, usize> {
+        AnnotatedPostOrderIter::new(self.first_child())
+    }
+
+
+    /// Get an iterator over the descendants of the node that have no children or an empty children layer, yielding each with its depth below the children
+    pub fn leaf_nodes_with_depth(&self) -> AnnotatedLeafIter<'_, T, usize> {
+        AnnotatedLeafIter { nodes: AnnotatedDFSIter::new(self.first_child()) }
+    }
+
+
+    /// Get a depth first search iterator over the descendants of the node, yielding each with its path from the children
+    pub fn dfs_nodes_with_path(&self) -> AnnotatedDFSIter<'_, T, BushPath> {
+        AnnotatedDFSIter::new(self.first_child())
+    }
+
+
+    /// Get a breadth first search iterator over the descendants of the node, yielding each with its path from the children
+    pub fn bfs_nodes_with_path(&self) -> AnnotatedBFSIter<'_, T, BushPath> {
+        AnnotatedBFSIter::new(self.first_child())
+    }
+
+
+    /// Get a post-order iterator over the descendants of the node, yielding each with its path from the children
+    pub fn post_order_nodes_with_path(&self) -> AnnotatedPostOrderIter<'_, T, BushPath> {
+        AnnotatedPostOrderIter::new(self.first_child())
+    }
+
+
+    /// Get an iterator over the descendants of the node that have no children or an empty children layer, yielding each with its path from the children
+    pub fn leaf_nodes_with_path(&self) -> AnnotatedLeafIter<'_, T, BushPath> {
+        AnnotatedLeafIter { nodes: AnnotatedDFSIter::new(self.first_child()) }
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+
+    const NESTED: &str = "0 [1 [2, 3], 4], 5 [], 6 [7]";
+
+
+    fn with_items<'a, A>(nodes: impl Iterator<Item = (A, &'a BushNode<i32>)>) -> Vec<(A, i32)> {
+        nodes.map(|(annotation, node)| (annotation, node.item)).collect()
+    }
+
+
+    fn paths(paths: &[&[usize]]) -> Vec<BushPath> {
+        paths.iter().map(|&path| BushPath::from(path)).collect()
+    }
+
+
+    #[test]
+    fn post_order() {
+        let bush: Bush<i32> = test_util::bush(NESTED);
+
+        assert_eq!(bush.post_order_items().copied().collect::<Vec<_>>(), vec![2, 3, 1, 4, 0, 5, 7, 6]);
+        assert_eq!(bush[0].post_order_items().copied().collect::<Vec<_>>(), vec![2, 3, 1, 4]);
+        assert_eq!(bush[1].post_order_items().count(), 0);
+        assert_eq!(Bush::<i32>::new().post_order_items().count(), 0);
+    }
+
+
+    #[test]
+    fn leaves() {
+        let bush: Bush<i32> = test_util::bush(NESTED);
+
+        assert_eq!(bush.leaf_items().copied().collect::<Vec<_>>(), vec![2, 3, 4, 5, 7]);
+        assert_eq!(bush[2].leaf_items().copied().collect::<Vec<_>>(), vec![7]);
+
+        // A node with an empty children layer counts as a leaf, like one without children
+        assert!(bush[1].children().is_some_and(Bush::is_empty));
+        let nested: Bush<i32> = test_util::bush("0 [1 [], 2 [3]]");
+        assert_eq!(nested.leaf_items().copied().collect::<Vec<_>>(), vec![1, 3]);
+        assert_eq!(nested[0].leaf_items().copied().collect::<Vec<_>>(), vec![1, 3]);
+        assert_eq!(with_items(nested.leaf_nodes_with_depth()), vec![(1, 1), (2, 3)]);
+    }
+
+
+    #[test]
+    fn with_depth() {
+        let bush = test_util::bush(NESTED);
+
+        assert_eq!(with_items(bush.dfs_nodes_with_depth()), vec![(0, 0), (1, 1), (2, 2), (2, 3), (1, 4), (0, 5), (0, 6), (1, 7)]);
+        assert_eq!(with_items(bush.bfs_nodes_with_depth()), vec![(0, 0), (0, 5), (0, 6), (1, 1), (1, 4), (1, 7), (2, 2), (2, 3)]);
+        assert_eq!(with_items(bush.post_order_nodes_with_depth()), vec![(2, 2), (2, 3), (1, 1), (1, 4), (0, 0), (0, 5), (1, 7), (0, 6)]);
+        assert_eq!(with_items(bush.leaf_nodes_with_depth()), vec![(2, 2), (2, 3), (1, 4), (0, 5), (1, 7)]);
+
+        // Depths are relative to the node's children layer
+        assert_eq!(with_items(bush[0].dfs_nodes_with_depth()), vec![(0, 1), (1, 2), (1, 3), (0, 4)]);
+        assert!(bush.dfs_nodes_with_depth().all(|(depth, node)| depth == node.depth()));
+    }
+
+
+    #[test]
+    fn with_path() {
+        let bush: Bush<i32> = test_util::bush(NESTED);
+
+        let dfs = bush.dfs_nodes_with_path().map(|(path, _)| path).collect::<Vec<_>>();
+        assert_eq!(dfs, paths(&[&[0], &[0, 0], &[0, 0, 0], &[0, 0, 1], &[0, 1], &[1], &[2], &[2, 0]]));
+
+        let bfs = bush.bfs_nodes_with_path().map(|(path, _)| path).collect::<Vec<_>>();
+        assert_eq!(bfs, paths(&[&[0], &[1], &[2], &[0, 0], &[0, 1], &[2, 0], &[0, 0, 0], &[0, 0, 1]]));
+
+        let leaves = bush.leaf_nodes_with_path().map(|(path, _)| path).collect::<Vec<_>>();
+        assert_eq!(leaves, paths(&[&[0, 0, 0], &[0, 0, 1], &[0, 1], &[1], &[2, 0]]));
+
+        // Every path resolves back to the node it was yielded with
+        for (path, node) in bush.post_order_nodes_with_path() {
+            assert_eq!(unsafe { bush.path_of(node.into_handle()) }, Some(path));
+        }
+
+        let children = bush[0].children().unwrap();
+        assert!(bush[0].post_order_nodes_with_path().all(|(path, node)| std::ptr::eq(&children[&path], node)));
+    }
+
+
+    #[test]
+    fn levels() {
+        let bush = test_util::bush(NESTED);
+
+        let levels: Vec<Vec<i32>> = bush.levels()
+            .map(|level| level.into_iter().map(|node| node.item).collect())
+            .collect();
+        assert_eq!(levels, vec![vec![0, 5, 6], vec![1, 4, 7], vec![2, 3]]);
+        assert_eq!(bush.levels().flatten().count(), bush.total_node_count());
+        assert_eq!(Bush::<i32>::new().levels().count(), 0);
+    }
+
+
+    #[test]
+    fn level_below() {
+        let bush = test_util::bush(NESTED);
+        let items = |nodes: Vec<&BushNode<i32>>| nodes.into_iter().map(|node| node.item).collect::<Vec<_>>();
+
+        assert_eq!(items(bush[0].level_below(0)), vec![0]);
+        assert_eq!(items(bush[0].level_below(1)), vec![1, 4]);
+        assert_eq!(items(bush[0].level_below(2)), vec![2, 3]);
+        assert!(bush[0].level_below(3).is_empty());
+        assert!(bush[1].level_below(1).is_empty());
+    }
+
+
+    #[test]
+    fn deep_post_order() {
+        const DEPTH: usize = if cfg!(miri) { 1_000 } else { 100_000 };
+
+        let bush = test_util::chain(DEPTH);
+
+        assert!(bush.post_order_items().copied().eq((0..DEPTH).rev()));
+        assert_eq!(bush.leaf_nodes_with_depth().map(|(depth, _)| depth).collect::<Vec<_>>(), vec![DEPTH - 1]);
+    }
+
+}
@@ -0,0 +1,235 @@
+use std::collections::VecDeque;
+
+use super::{Bush, BushNode};
+
+
+/// What a walk should do after visiting a node
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WalkControl {
+
+    /// Keep walking normally
+    Continue,
+
+    /// Don't visit the children of the node
+    SkipChildren,
+
+    /// Don't visit the nodes to the right of the node, nor their branches.
+    /// The children of the node are still visited.
+    SkipSiblings,
+
+    /// End the walk
+    Stop,
+
+}
+
+
+impl WalkControl {
+
+    fn walk_children(self) -> bool {
+        matches!(self, WalkControl::Continue | WalkControl::SkipSiblings)
+    }
+
+
+    fn walk_siblings(self) -> bool {
+        matches!(self, WalkControl::Continue | WalkControl::SkipChildren)
+    }
+
+}
+
+
+/// Walk depth first from `first` and its right siblings, calling `visit` with each node and its depth
+fn walk_dfs<'a, T, F>(first: Option<&'a BushNode<T>>, mut visit: F)
+where
+    F: FnMut(usize, &'a BushNode<T>) -> WalkControl
+{
+    let mut nodes: Vec<(usize, &BushNode<T>)> = first.map(|node| (0, node)).into_iter().collect();
+
+    while let Some((depth, node)) = nodes.pop() {
+        let control = visit(depth, node);
+        if control == WalkControl::Stop {
+            return;
+        }
+
+        // Push the right sibling first, so that the children are walked before it
+        if control.walk_siblings() {
+            if let Some(right) = node.right_node() {
+                nodes.push((depth, right));
+            }
+        }
+        if control.walk_children() {
+            if let Some(first) = node.children().and_then(Bush::first_node) {
+                nodes.push((depth + 1, first));
+            }
+        }
+    }
+}
+
+
+/// Walk breadth first from `first` and its right siblings, calling `visit` with each node and its depth
+fn walk_bfs<'a, T, F>(first: Option<&'a BushNode<T>>, mut visit: F)
+where
+    F: FnMut(usize, &'a BushNode<T>) -> WalkControl
+{
+    let mut nodes: VecDeque<(usize, &BushNode<T>)> = first.map(|node| (0, node)).into_iter().collect();
+
+    while let Some((depth, node)) = nodes.pop_front() {
+        let control = visit(depth, node);
+        if control == WalkControl::Stop {
+            return;
+        }
+
+        // Same-layer nodes go on the front and lower-layer nodes on the back, like in `BFSIter`
+        if control.walk_siblings() {
+            if let Some(right) = node.right_node() {
+                nodes.push_front((depth, right));
+            }
+        }
+        if control.walk_children() {
+            if let Some(first) = node.children().and_then(Bush::first_node) {
+                nodes.push_back((depth + 1, first));
+            }
+        }
+    }
+}
+
+
+impl<T> Bush<T> {
+
+    /// Walk the bush depth first, calling `visit` with each node and its depth.
+    /// The value returned by `visit` decides which nodes are visited next.
+    pub fn walk_dfs<'a, F>(&'a self, visit: F)
+    where
+        F: FnMut(usize, &'a BushNode<T>) -> WalkControl
+    {
+        walk_dfs(self.first_node(), visit);
+    }
+
+
+    /// Walk the bush breadth first, calling `visit` with each node and its depth.
+    /// The value returned by `visit` decides which nodes are visited next.
+    pub fn walk_bfs<'a, F>(&'a self, visit: F)
+    where
+        F: FnMut(usize, &'a BushNode<T>) -> WalkControl
+    {
+        walk_bfs(self.first_node(), visit);
+    }
+
+}
+
+
+impl<T> BushNode<T> {
+
+    /// Walk the descendants of the node depth first, calling `visit` with each node and its depth.
+    /// Depths are relative to the node's children layer.
+    pub fn walk_dfs<'a, F>(&'a self, visit: F)
+    where
+        F: FnMut(usize, &'a BushNode<T>) -> WalkControl
+    {
+        walk_dfs(self.children().and_then(Bush::first_node), visit);
+    }
+
+
+    /// Walk the descendants of the node breadth first, calling `visit` with each node and its depth.
+    /// Depths are relative to the node's children layer.
+    pub fn walk_bfs<'a, F>(&'a self, visit: F)
+    where
+        F: FnMut(usize, &'a BushNode<T>) -> WalkControl
+    {
+        walk_bfs(self.children().and_then(Bush::first_node), visit);
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+
+    const NESTED: &str = "0 [1 [2, 3], 4], 5, 6 [7 [8]]";
+
+
+    fn walk_dfs_with(bush: &Bush<i32>, control: impl Fn(usize, i32) -> WalkControl) -> Vec<i32> {
+        let mut visited = Vec::new();
+        bush.walk_dfs(|depth, node| {
+            visited.push(node.item);
+            control(depth, node.item)
+        });
+        visited
+    }
+
+
+    fn walk_bfs_with(bush: &Bush<i32>, control: impl Fn(usize, i32) -> WalkControl) -> Vec<i32> {
+        let mut visited = Vec::new();
+        bush.walk_bfs(|depth, node| {
+            visited.push(node.item);
+            control(depth, node.item)
+        });
+        visited
+    }
+
+
+    #[test]
+    fn walk_dfs() {
+        let bush = test_util::bush(NESTED);
+
+        assert_eq!(walk_dfs_with(&bush, |_, _| WalkControl::Continue), bush.dfs_items().copied().collect::<Vec<_>>());
+        assert_eq!(walk_dfs_with(&bush, |_, item| if item == 1 { WalkControl::SkipChildren } else { WalkControl::Continue }), vec![0, 1, 4, 5, 6, 7, 8]);
+        assert_eq!(walk_dfs_with(&bush, |_, item| if item == 2 { WalkControl::SkipSiblings } else { WalkControl::Continue }), vec![0, 1, 2, 4, 5, 6, 7, 8]);
+        assert_eq!(walk_dfs_with(&bush, |_, item| if item == 5 { WalkControl::SkipSiblings } else { WalkControl::Continue }), vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(walk_dfs_with(&bush, |_, item| if item == 3 { WalkControl::Stop } else { WalkControl::Continue }), vec![0, 1, 2, 3]);
+        assert_eq!(walk_dfs_with(&bush, |depth, _| if depth == 1 { WalkControl::SkipChildren } else { WalkControl::Continue }), vec![0, 1, 4, 5, 6, 7]);
+    }
+
+
+    #[test]
+    fn walk_bfs() {
+        let bush = test_util::bush(NESTED);
+
+        assert_eq!(walk_bfs_with(&bush, |_, _| WalkControl::Continue), bush.bfs_items().copied().collect::<Vec<_>>());
+        assert_eq!(walk_bfs_with(&bush, |_, item| if item == 0 { WalkControl::SkipChildren } else { WalkControl::Continue }), vec![0, 5, 6, 7, 8]);
+        assert_eq!(walk_bfs_with(&bush, |_, item| if item == 5 { WalkControl::SkipSiblings } else { WalkControl::Continue }), vec![0, 5, 1, 4, 2, 3]);
+        assert_eq!(walk_bfs_with(&bush, |_, item| if item == 4 { WalkControl::Stop } else { WalkControl::Continue }), vec![0, 5, 6, 1, 4]);
+    }
+
+
+    #[test]
+    fn node_walk() {
+        let bush = test_util::bush(NESTED);
+
+        let mut visited = Vec::new();
+        bush[0].walk_dfs(|depth, node| {
+            visited.push((depth, node.item));
+            WalkControl::Continue
+        });
+        assert_eq!(visited, vec![(0, 1), (1, 2), (1, 3), (0, 4)]);
+
+        let mut visited = Vec::new();
+        bush[2].walk_bfs(|depth, node| {
+            visited.push((depth, node.item));
+            WalkControl::Continue
+        });
+        assert_eq!(visited, vec![(0, 7), (1, 8)]);
+    }
+
+
+    #[test]
+    fn depth_limits() {
+        let bush = test_util::bush(NESTED);
+
+        let items = |nodes: &mut dyn Iterator<Item = &BushNode<i32>>| nodes.map(|node| node.item).collect::<Vec<_>>();
+
+        assert_eq!(items(&mut bush.dfs_nodes().max_depth(0)), vec![0, 5, 6]);
+        assert_eq!(items(&mut bush.dfs_nodes().max_depth(1)), vec![0, 1, 4, 5, 6, 7]);
+        assert_eq!(items(&mut bush.dfs_nodes().min_depth(1)), vec![1, 2, 3, 4, 7, 8]);
+        assert_eq!(items(&mut bush.dfs_nodes().min_depth(1).max_depth(1)), vec![1, 4, 7]);
+        assert_eq!(items(&mut bush.bfs_nodes().max_depth(1)), vec![0, 5, 6, 1, 4, 7]);
+        assert_eq!(items(&mut bush.bfs_nodes().min_depth(2)), vec![2, 3, 8]);
+        assert_eq!(items(&mut bush.bfs_nodes().min_depth(3)), Vec::<i32>::new());
+
+        // Node traversals count depths from the node's children layer
+        assert_eq!(items(&mut bush[0].dfs_nodes().max_depth(0)), vec![1, 4]);
+        assert_eq!(items(&mut bush[2].bfs_nodes().min_depth(1)), vec![8]);
+    }
+
+}
@@ -0,0 +1,153 @@
+use std::ptr;
+
+use super::{link_run, node_layer, unlink_run, Bush, BushNode, NodeHandle};
+
+
+impl<T> Bush<T> {
+
+    /// Replace the run of sibling nodes from `start` to `end` with a new node holding `item`,
+    /// and make the run, with its branches, the children of the new node.
+    /// The run can be in any layer of the bush. Return a handle to the new node.
+    ///
+    /// Panics if the nodes are not in the bush, or if `end` is not `start` or a node to its right.
+    ///
+    /// # Safety
+    ///
+    /// Both handles must refer to nodes that have not been removed. A node of another bush must not be mutably borrowed during the call.
+    pub unsafe fn wrap_range(&mut self, start: NodeHandle<T>, end: NodeHandle<T>, item: T) -> NodeHandle<T> {
+        let start = start.as_ptr() as *mut BushNode<T>;
+        let end = end.as_ptr() as *mut BushNode<T>;
+        assert!(self.owns_node(start), "the start node is not in the bush");
+
+        let mut node = start;
+        while !ptr::addr_eq(node, end) {
+            assert!(!node.is_null(), "the end node does not follow the start node in the layer");
+            node = (*node).right;
+        }
+
+        let layer = node_layer(start);
+        let left = (*start).left;
+        let right = (*end).right;
+        unlink_run(start, end);
+
+        let wrapper = Box::into_raw(BushNode::new(item));
+        link_run(layer, left, right, wrapper, wrapper);
+        (*wrapper).set_children(Some(Bush::from_run(start, end)));
+
+        NodeHandle(wrapper)
+    }
+
+
+    /// Remove the given node, put its children, with their branches, in its place, and return its item.
+    /// The node can be in any layer of the bush.
+    ///
+    /// Panics if the node is not in the bush.
+    ///
+    /// # Safety
+    ///
+    /// The handle must refer to a node that has not been removed. A node of another bush must not be mutably borrowed during the call.
+    pub unsafe fn unwrap_node(&mut self, node: NodeHandle<T>) -> T {
+        let node = node.as_ptr() as *mut BushNode<T>;
+        assert!(self.owns_node(node), "the node is not in the bush");
+
+        let children = (*node).take_children();
+
+        let layer = node_layer(node);
+        let left = (*node).left;
+        let right = (*node).right;
+        unlink_run(node, node);
+
+        if let Some((first, last)) = children.and_then(|mut children| children.take_run()) {
+            link_run(layer, left, right, first, last);
+        }
+
+        Box::from_raw(node).item
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::super::BushPath;
+    use super::*;
+    use crate::test_util::render;
+
+    #[test]
+    fn wrap_range() {
+        let mut bush: Bush<char> = "abcde".chars().collect();
+        bush[2].set_children(Some("xy".chars().collect()));
+
+        let group = unsafe { bush.wrap_range(bush[1].into_handle(), bush[3].into_handle(), 'G') };
+        assert_eq!(render(&bush), "aG[bc[xy]d]e");
+        assert_eq!(bush.len(), 3);
+        assert_eq!(bush.total_node_count(), 8);
+        assert_eq!(unsafe { bush.node(group) }.item, 'G');
+        assert_eq!(bush[1].children().unwrap()[1].parent_node().map(|node| node.item), Some('G'));
+
+        // Wrapping a whole layer updates both of its ends
+        let all = unsafe { bush.wrap_range(bush[0].into_handle(), bush[2].into_handle(), 'R') };
+        assert_eq!(render(&bush), "R[aG[bc[xy]d]e]");
+        assert!(bush.first_node_handle() == Some(all));
+        assert!(bush.last_node_handle() == Some(all));
+    }
+
+
+    #[test]
+    fn wrap_nested_range() {
+        let mut bush: Bush<char> = "ab".chars().collect();
+        bush[1].set_children(Some("xyz".chars().collect()));
+
+        let children = bush[1].children().unwrap();
+        let (x, y) = (children[0].into_handle(), children[1].into_handle());
+        unsafe { bush.wrap_range(x, y, 'G') };
+        assert_eq!(render(&bush), "ab[G[xy]z]");
+        assert_eq!(bush.total_node_count(), 6);
+
+        let single = bush[1].children().unwrap()[1].into_handle();
+        unsafe { bush.wrap_range(single, single, 'H') };
+        assert_eq!(render(&bush), "ab[G[xy]H[z]]");
+    }
+
+
+    #[test]
+    #[should_panic(expected = "does not follow")]
+    fn wrap_reversed_range() {
+        let mut bush: Bush<char> = "abc".chars().collect();
+        unsafe { bush.wrap_range(bush[2].into_handle(), bush[0].into_handle(), 'G') };
+    }
+
+
+    #[test]
+    fn unwrap_node() {
+        let mut bush: Bush<char> = "aGe".chars().collect();
+        bush[1].set_children(Some("bcd".chars().collect()));
+        bush[&BushPath::from([1, 1])].set_children(Some("xy".chars().collect()));
+
+        // Unwrap a nested node, then the node holding it
+        let c = bush[1].children().unwrap()[1].into_handle();
+        assert_eq!(unsafe { bush.unwrap_node(c) }, 'c');
+        assert_eq!(render(&bush), "aG[bxyd]e");
+
+        assert_eq!(unsafe { bush.unwrap_node(bush[1].into_handle()) }, 'G');
+        assert_eq!(render(&bush), "abxyde");
+        assert_eq!(bush.total_node_count(), 6);
+        assert_eq!(bush[3].parent_node().map(|node| node.item), None);
+
+        // Unwrapping a leaf at the end of the layer just removes it
+        assert_eq!(unsafe { bush.unwrap_node(bush[5].into_handle()) }, 'e');
+        assert_eq!(bush.last_item(), Some(&'d'));
+    }
+
+
+    #[test]
+    fn wrap_then_unwrap() {
+        let mut bush: Bush<char> = "abcd".chars().collect();
+        let group = unsafe { bush.wrap_range(bush[0].into_handle(), bush[3].into_handle(), 'G') };
+        assert_eq!(unsafe { bush.unwrap_node(group) }, 'G');
+        assert_eq!(render(&bush), "abcd");
+        assert_eq!(bush.first_item(), Some(&'a'));
+        assert_eq!(bush.last_item(), Some(&'d'));
+    }
+
+}
@@ -1,9 +1,22 @@
-use crate::bush::{Bush, BushNode};
+use std::fmt;
+use std::marker::PhantomData;
 
-use serde::{Deserialize, Serialize};
+use crate::bush::{Bush, BushNode, BushPath};
+
+use serde::{Deserialize, Deserializer, Serialize};
+use serde::de::{self, IgnoredAny, MapAccess, SeqAccess, Visitor};
 use serde::ser::{SerializeSeq, SerializeStruct};
 
 
+// Bushes serialize as nested sequences of `{item, children}` nodes, where `children` is null for a node without
+// children. Each layer is written and read with a loop, but serde serializers and deserializers recurse once per
+// nested value, and some formats limit the nesting depth, so very deep bushes should use the `flat` format instead.
+
+
+/// Field names of a serialized bush node, in serialization order
+const NODE_FIELDS: &[&str] = &["item", "children"];
+
+
 impl<T> Serialize for Bush<T>
 where
     T: Serialize
@@ -14,8 +27,8 @@ where
         S: serde::Serializer
     {
         let mut seq = serializer.serialize_seq(Some(self.top_layer_length()))?;
-        for item in self.iter_nodes() {
-            seq.serialize_element(item)?;
+        for node in self.iter_nodes() {
+            seq.serialize_element(node)?;
         }
         seq.end()
     }
@@ -34,44 +47,531 @@ where
     {
         let mut state = serializer.serialize_struct("BushNode", 2)?;
         state.serialize_field("item", &self.item)?;
-        state.serialize_field("children", &self.children)?;
+        state.serialize_field("children", &self.children())?;
         state.end()
     }
     
 }
 
 
-// impl<'de, T> Deserialize<'de> for Bush<T>
-// where
-//     T: Deserialize<'a>
-// {
+impl<'de, T> Deserialize<'de> for Bush<T>
+where
+    T: Deserialize<'de>
+{
+
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> 
+    where
+        D: Deserializer<'de>
+    {
+        deserializer.deserialize_seq(BushVisitor(PhantomData))
+    }
+
+}
+
+
+/// `BushNode` is unsized so that nodes can't be moved out of their box, and `Deserialize` requires a sized type,
+/// so nodes deserialize boxed, the way `BushNode::new` creates them
+impl<'de, T> Deserialize<'de> for Box<BushNode<T>>
+where
+    T: Deserialize<'de>
+{
+
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where 
+        D: Deserializer<'de>
+    {
+        deserializer.deserialize_struct("BushNode", NODE_FIELDS, BushNodeVisitor(PhantomData))
+    }
 
-//     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> 
-//     where
-//         D: serde::Deserializer<'de>
-//     {
-//         let mut bush = Bush::new();
-//         let mut seq = <Vec<BushNode<T>>>::deserialize(deserializer)?;
-//         for node in seq.drain(..) {
-//             bush.add_node(node);
-//         }
-//         Ok(bush)
-//     }
+}
+
+
+struct BushVisitor<T>(PhantomData<T>);
+
+impl<'de, T> Visitor<'de> for BushVisitor<T>
+where
+    T: Deserialize<'de>
+{
+    type Value = Bush<T>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a sequence of bush nodes")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>
+    {
+        let mut bush = Bush::new();
+        while let Some(node) = seq.next_element::<Box<BushNode<T>>>()? {
+            bush.append_node(node);
+        }
+        Ok(bush)
+    }
+}
+
+
+struct BushNodeVisitor<T>(PhantomData<T>);
+
+impl<'de, T> Visitor<'de> for BushNodeVisitor<T>
+where
+    T: Deserialize<'de>
+{
+    type Value = Box<BushNode<T>>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a bush node")
+    }
+
+    /// Non-self-describing formats serialize structs as a sequence of fields
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>
+    {
+        let item = seq.next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let children = seq.next_element()?
+            .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+
+        let mut node = BushNode::new(item);
+        node.set_children(children);
+        Ok(node)
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>
+    {
+        let mut item = None;
+        let mut children = None;
+
+        while let Some(field) = map.next_key()? {
+            match field {
+                NodeField::Item => {
+                    if item.is_some() {
+                        return Err(de::Error::duplicate_field("item"));
+                    }
+                    item = Some(map.next_value()?);
+                },
+                NodeField::Children => {
+                    if children.is_some() {
+                        return Err(de::Error::duplicate_field("children"));
+                    }
+                    children = Some(map.next_value()?);
+                },
+                NodeField::Other => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+
+        let item = item.ok_or_else(|| de::Error::missing_field("item"))?;
+
+        let mut node = BushNode::new(item);
+        // A missing `children` field is treated like `children: null`
+        node.set_children(children.flatten());
+        Ok(node)
+    }
+}
+
+
+/// Identifier of a field in a serialized bush node
+enum NodeField {
+    Item,
+    Children,
+    Other
+}
+
+impl<'de> Deserialize<'de> for NodeField {
+
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>
+    {
+        deserializer.deserialize_identifier(NodeFieldVisitor)
+    }
+
+}
+
+
+struct NodeFieldVisitor;
+
+impl<'de> Visitor<'de> for NodeFieldVisitor {
+    type Value = NodeField;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a bush node field identifier")
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
+    where
+        E: de::Error
+    {
+        Ok(match value {
+            0 => NodeField::Item,
+            1 => NodeField::Children,
+            _ => NodeField::Other
+        })
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error
+    {
+        Ok(match value {
+            "item" => NodeField::Item,
+            "children" => NodeField::Children,
+            _ => NodeField::Other
+        })
+    }
+
+    fn visit_bytes<E>(self, value: &[u8]) -> Result<Self::Value, E>
+    where
+        E: de::Error
+    {
+        Ok(match value {
+            b"item" => NodeField::Item,
+            b"children" => NodeField::Children,
+            _ => NodeField::Other
+        })
+    }
+}
+
+
+// Paths serialize as the plain sequence of their sibling indices
+
+impl Serialize for BushPath {
+
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer
+    {
+        self.indices().serialize(serializer)
+    }
+
+}
+
+
+impl<'de> Deserialize<'de> for BushPath {
+
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>
+    {
+        Vec::<usize>::deserialize(deserializer).map(BushPath::from)
+    }
+
+}
 
-// }
 
+/// Serialize a bush as the flat sequence of its nodes' `(depth, item, has_children)` triples, in depth first order.
+///
+/// `has_children` tells a node with an empty children layer from a node without one. Unlike the nested format,
+/// neither direction recurses per layer, so bushes of any depth can be serialized.
+/// Use it on a field with `#[serde(with = "rs_bush::json::flat")]`.
+pub mod flat {
 
-// impl<'de, T> Deserialize<'de> for BushNode<T>
-// where
-//     T: Deserialize<'de>
-// {
+    use std::fmt;
+    use std::marker::PhantomData;
 
-//     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-//     where 
-//         D: serde::Deserializer<'de>
-//     {
-//         let mut state = deserializer.deserialize_struct("BushNode", &["item", "children"], BushNodeVisitor)?;
-//     }
+    use crate::bush::Bush;
 
-// }
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use serde::de::{self, SeqAccess, Visitor};
+    use serde::ser::SerializeSeq;
 
+
+    pub fn serialize<T, S>(bush: &Bush<T>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: Serialize,
+        S: Serializer
+    {
+        let mut seq = serializer.serialize_seq(Some(bush.total_node_count()))?;
+
+        // Iterators over the layers on the path to the current node
+        let mut layers = vec![bush.iter_nodes()];
+
+        while let Some(layer) = layers.last_mut() {
+            match layer.next() {
+                Some(node) => {
+                    seq.serialize_element(&(layers.len() - 1, &node.item, node.children().is_some()))?;
+                    if let Some(children) = node.children() {
+                        layers.push(children.iter_nodes());
+                    }
+                },
+                None => {
+                    layers.pop();
+                }
+            }
+        }
+
+        seq.end()
+    }
+
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Bush<T>, D::Error>
+    where
+        T: Deserialize<'de>,
+        D: Deserializer<'de>
+    {
+        deserializer.deserialize_seq(FlatBushVisitor(PhantomData))
+    }
+
+
+    struct FlatBushVisitor<T>(PhantomData<T>);
+
+    impl<'de, T> Visitor<'de> for FlatBushVisitor<T>
+    where
+        T: Deserialize<'de>
+    {
+        type Value = Bush<T>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a sequence of (depth, item, has_children) triples")
+        }
+
+        fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+        where
+            A: SeqAccess<'de>
+        {
+            let mut bush = Bush::new();
+            let mut cursor = bush.cursor_mut();
+
+            // Depth of the node the cursor is on, None until the first node is inserted
+            let mut cursor_depth: Option<usize> = None;
+
+            while let Some((depth, item, has_children)) = seq.next_element::<(usize, T, bool)>()? {
+                match cursor_depth {
+                    Some(current) if depth == current + 1 && cursor.current_node().unwrap().children().is_some() => {
+                        // First child of the current node
+                        cursor.move_into_children();
+                        cursor.insert_before(item);
+                        cursor.move_left();
+                    },
+                    Some(current) if depth <= current => {
+                        // Next sibling of the current node or of one of its ancestors
+                        for _ in depth..current {
+                            cursor.move_to_parent();
+                        }
+                        cursor.insert_after(item);
+                        cursor.move_right();
+                    },
+                    None if depth == 0 => {
+                        cursor.insert_before(item);
+                        cursor.move_left();
+                    },
+                    _ => {
+                        return Err(de::Error::custom(format_args!("node at depth {} has no parent", depth)));
+                    }
+                }
+                if has_children {
+                    cursor.current_node().unwrap().set_children(Some(Bush::new()));
+                }
+                cursor_depth = Some(depth);
+            }
+
+            Ok(bush)
+        }
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{self, chain, wide_bush};
+
+    /// Assert that two bushes have the same items and the same nesting, layer by layer
+    fn assert_same_shape(a: &Bush<usize>, b: &Bush<usize>) {
+        assert_eq!(a.top_layer_length(), b.top_layer_length());
+        for (node_a, node_b) in a.iter_nodes().zip(b.iter_nodes()) {
+            assert_eq!(node_a.item, node_b.item);
+            match (node_a.children(), node_b.children()) {
+                (Some(children_a), Some(children_b)) => assert_same_shape(children_a, children_b),
+                (None, None) => {},
+                _ => panic!("children mismatch at item {}", node_a.item)
+            }
+        }
+    }
+
+
+    #[test]
+    fn json_round_trip() {
+        let bush = wide_bush(4);
+        let json = serde_json::to_string(&bush).unwrap();
+        let deserialized: Bush<usize> = serde_json::from_str(&json).unwrap();
+
+        assert_same_shape(&bush, &deserialized);
+        assert_eq!(deserialized.total_node_count(), bush.total_node_count());
+        assert_eq!(serde_json::to_string(&deserialized).unwrap(), json);
+    }
+
+
+    #[test]
+    fn json_format() {
+        let bush: Bush<i32> = test_util::bush("0 [1, 2 []], 3");
+        let json = serde_json::to_string(&bush).unwrap();
+        assert_eq!(
+            json,
+            r#"[{"item":0,"children":[{"item":1,"children":null},{"item":2,"children":[]}]},{"item":3,"children":null}]"#
+        );
+        assert_eq!(serde_json::from_str::<Bush<i32>>(&json).unwrap(), bush);
+    }
+
+
+    #[test]
+    fn json_baseline_format() {
+        let json = r#"[
+            {"item": 0, "children": [{"item": 1, "children": null}, {"item": 2, "children": [{"item": 3, "children": null}]}]},
+            {"item": 4, "children": null}
+        ]"#;
+        let bush: Bush<i32> = serde_json::from_str(json).unwrap();
+        assert_eq!(bush, test_util::bush("0 [1, 2 [3]], 4"));
+        assert_eq!(bush[&BushPath::from([0, 1, 0])].ancestor_items().copied().collect::<Vec<_>>(), vec![2, 0]);
+    }
+
+
+    #[test]
+    fn json_node() {
+        let json = r#"{"item":2,"children":[{"item":3,"children":null}]}"#;
+        let node: Box<BushNode<i32>> = serde_json::from_str(json).unwrap();
+        assert_eq!(node.children().and_then(Bush::first_item), Some(&3));
+        assert_eq!(serde_json::to_string(&node).unwrap(), json);
+
+        let node: Box<BushNode<i32>> = serde_json::from_str(r#"{"item":1,"children":null}"#).unwrap();
+        assert!(node.children().is_none());
+        let node: Box<BushNode<i32>> = serde_json::from_str(r#"{"item":1}"#).unwrap();
+        assert!(node.children().is_none());
+    }
+
+
+    #[test]
+    fn node_round_trip() {
+        let bush: Bush<i32> = test_util::bush("0 [1, 2 [], 3 [4]]");
+
+        for node in [bush[0].to_owned(), BushNode::new(5)] {
+            let json = serde_json::to_string(&node).unwrap();
+            assert_eq!(serde_json::from_str::<Box<BushNode<i32>>>(&json).unwrap(), node);
+
+            let bytes = bincode::serialize(&node).unwrap();
+            assert_eq!(bincode::deserialize::<Box<BushNode<i32>>>(&bytes).unwrap(), node);
+        }
+
+        let node: Box<BushNode<i32>> = serde_json::from_str(&serde_json::to_string(&bush[0]).unwrap()).unwrap();
+        assert!(node.children().unwrap()[1].children().is_some_and(Bush::is_empty));
+        assert!(node.children().unwrap()[2].children().unwrap()[0].parent_item().is_some_and(|item| *item == 3));
+    }
+
+
+    #[test]
+    fn json_missing_item() {
+        let json = r#"{"children":null}"#;
+        assert!(serde_json::from_str::<Box<BushNode<i32>>>(json).is_err());
+    }
+
+
+    #[test]
+    fn json_empty_bush() {
+        let bush: Bush<i32> = serde_json::from_str("[]").unwrap();
+        assert!(bush.is_empty());
+    }
+
+
+    #[test]
+    fn bincode_round_trip() {
+        let bush = wide_bush(4);
+        let bytes = bincode::serialize(&bush).unwrap();
+        let deserialized: Bush<usize> = bincode::deserialize(&bytes).unwrap();
+
+        assert_same_shape(&bush, &deserialized);
+        assert_eq!(deserialized.total_node_count(), bush.total_node_count());
+        assert_eq!(bincode::serialize(&deserialized).unwrap(), bytes);
+    }
+
+
+    #[test]
+    fn bincode_round_trip_single_node() {
+        let mut bush = Bush::new();
+        bush.append(String::from("leaf"));
+
+        let bytes = bincode::serialize(&bush).unwrap();
+        let deserialized: Bush<String> = bincode::deserialize(&bytes).unwrap();
+
+        assert_eq!(deserialized.top_layer_length(), 1);
+        assert_eq!(deserialized.first_item().map(String::as_str), Some("leaf"));
+        assert!(deserialized.first_node().unwrap().children().is_none());
+    }
+
+
+    #[test]
+    fn path_round_trip() {
+        let path = BushPath::from([0, 2, 1]);
+        let json = serde_json::to_string(&path).unwrap();
+        assert_eq!(json, "[0,2,1]");
+        assert_eq!(serde_json::from_str::<BushPath>(&json).unwrap(), path);
+    }
+
+
+    #[test]
+    fn nested_chain() {
+        // Each layer nests a sequence and a struct, and serde_json rejects input nested over 128 levels deep
+        let bush = chain(60);
+
+        let json = serde_json::to_string(&bush).unwrap();
+        assert_eq!(serde_json::from_str::<Bush<usize>>(&json).unwrap(), bush);
+    }
+
+
+    /// A bush in the flat format, serialized like a field marked `#[serde(with = "flat")]`
+    #[derive(Debug, PartialEq)]
+    struct Flat<T>(Bush<T>);
+
+    impl<T: Serialize> Serialize for Flat<T> {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            flat::serialize(&self.0, serializer)
+        }
+    }
+
+    impl<'de, T: Deserialize<'de>> Deserialize<'de> for Flat<T> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            flat::deserialize(deserializer).map(Flat)
+        }
+    }
+
+
+    #[test]
+    fn flat_format() {
+        let bush = Flat(test_util::bush("0 [1, 2 []], 3"));
+        let json = serde_json::to_string(&bush).unwrap();
+        assert_eq!(json, "[[0,0,true],[1,1,false],[1,2,true],[0,3,false]]");
+        assert_eq!(serde_json::from_str::<Flat<i32>>(&json).unwrap(), bush);
+
+        let bytes = bincode::serialize(&bush).unwrap();
+        assert_eq!(bincode::deserialize::<Flat<i32>>(&bytes).unwrap(), bush);
+        assert!(serde_json::from_str::<Flat<i32>>("[]").unwrap().0.is_empty());
+    }
+
+
+    #[test]
+    fn flat_missing_parent() {
+        assert!(serde_json::from_str::<Flat<i32>>("[[0,0,true],[2,1,false]]").is_err());
+        assert!(serde_json::from_str::<Flat<i32>>("[[0,0,false],[1,1,false]]").is_err());
+        assert!(serde_json::from_str::<Flat<i32>>("[[1,0,false]]").is_err());
+    }
+
+
+    #[test]
+    fn flat_deep_chain() {
+        const DEPTH: usize = if cfg!(miri) { 100 } else { 100_000 };
+
+        let bush = Flat(chain(DEPTH));
+
+        let json = serde_json::to_string(&bush).unwrap();
+        assert_eq!(serde_json::from_str::<Flat<usize>>(&json).unwrap(), bush);
+
+        let bytes = bincode::serialize(&bush).unwrap();
+        assert_eq!(bincode::deserialize::<Flat<usize>>(&bytes).unwrap(), bush);
+    }
+
+}
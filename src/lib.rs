@@ -5,3 +5,5 @@ pub mod bush;
 #[cfg(feature = "serde")]
 pub mod json;
 
+#[cfg(test)]
+mod test_util;
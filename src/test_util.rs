@@ -0,0 +1,125 @@
+//! Fixtures shared by the unit tests of every module
+
+use std::fmt::{Debug, Display};
+use std::str::FromStr;
+
+use crate::bush::{Bush, NodeHandle};
+
+
+/// Build a bush from its written form, like `0 [1 [2, 3], 4], 5 [], 6 [7]`,
+/// where brackets enclose a node's children and items are separated by commas or whitespace.
+pub fn bush<T>(spec: &str) -> Bush<T>
+where
+    T: FromStr,
+    T::Err: Debug
+{
+    // The layers being filled, the innermost last
+    let mut layers = vec![Bush::new()];
+    let mut token = String::new();
+
+    for c in spec.chars().chain([' ']) {
+        if !(c == '[' || c == ']' || c == ',' || c.is_whitespace()) {
+            token.push(c);
+            continue;
+        }
+
+        if !token.is_empty() {
+            layers.last_mut().unwrap().append(token.parse().unwrap());
+            token.clear();
+        }
+        match c {
+            '[' => layers.push(Bush::new()),
+            ']' => {
+                let children = layers.pop().unwrap();
+                let parent = layers.last_mut().and_then(Bush::last_node_mut);
+                parent.unwrap_or_else(|| panic!("unexpected `]` in {spec:?}")).set_children(Some(children));
+            },
+            _ => {}
+        }
+    }
+
+    assert!(layers.len() == 1, "unclosed `[` in {spec:?}");
+    layers.pop().unwrap()
+}
+
+
+/// Build a bush with one node per character of `text`, for items that are brackets themselves
+pub fn chars(text: &str) -> Bush<char> {
+    text.chars().collect()
+}
+
+
+/// Get the items of the bush's top layer
+pub fn items<T: Clone>(bush: &Bush<T>) -> Vec<T> {
+    bush.iter_items().cloned().collect()
+}
+
+
+/// Write a bush in the compact form `a[b[c]d]ef[g]`, meant for single character items
+pub fn render<T: Display>(bush: &Bush<T>) -> String {
+    render_with(bush, "[", "]", "")
+}
+
+
+/// Write each node's item followed by its children between `open` and `close`, with `separator` between siblings
+pub fn render_with<T: Display>(bush: &Bush<T>, open: &str, close: &str, separator: &str) -> String {
+    let nodes: Vec<String> = bush.iter_nodes().map(|node| {
+        match node.children() {
+            Some(children) => format!("{}{open}{}{close}", node.item, render_with(children, open, close, separator)),
+            None => node.item.to_string()
+        }
+    }).collect();
+    nodes.join(separator)
+}
+
+
+/// Get a handle to the first node holding `item` in depth first order, at any depth
+pub fn handle<T: PartialEq>(bush: &Bush<T>, item: T) -> NodeHandle<T> {
+    bush.dfs_nodes().find(|node| node.item == item).unwrap().into_handle()
+}
+
+
+/// Build a bush where each node is the only child of the previous one
+pub fn chain(depth: usize) -> Bush<usize> {
+    let mut bush = Bush::new();
+    bush.append(0);
+
+    let mut cursor = bush.cursor_mut();
+    for i in 1..depth {
+        cursor.current_node().unwrap().set_children(Some(Bush::new()));
+        cursor.move_into_children();
+        cursor.insert_after(i);
+        cursor.move_right();
+    }
+
+    bush
+}
+
+
+/// Build a bush with `width` top-layer nodes, each owning a layer of `width` children,
+/// the first of which owns a further nested layer
+#[cfg(feature = "serde")]
+pub fn wide_bush(width: usize) -> Bush<usize> {
+    let mut bush = Bush::new();
+    let mut counter = 0;
+
+    for _ in 0..width {
+        bush.append(counter);
+        counter += 1;
+
+        let mut children = Bush::new();
+        for _ in 0..width {
+            children.append(counter);
+            counter += 1;
+        }
+
+        let mut grandchildren = Bush::new();
+        grandchildren.append(counter);
+        counter += 1;
+        children.first_node_mut().unwrap().set_children(Some(grandchildren));
+
+        bush.last_node_mut().unwrap().set_children(Some(children));
+    }
+
+    bush
+}